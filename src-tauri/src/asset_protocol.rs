@@ -0,0 +1,281 @@
+use crate::helper::load_config;
+use crate::image_cache::media_mime_type;
+use parking_lot::RwLock;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tauri::http::{header, Request, Response, StatusCode};
+
+/// Served as `readtext://localhost/<encoded path>`, the only way rendered
+/// content can load local files.
+pub const ASSET_SCHEME: &str = "readtext";
+
+/// Longest slice served per range request, so seeking in a large video
+/// does not read the whole file into memory.
+const MAX_RANGE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// What rendered content may load, besides the configured `search_paths`.
+#[derive(Debug, Default)]
+struct AssetScope {
+    /// The open document, below whose folder files may be loaded.
+    document: Option<PathBuf>,
+    /// Images and media the resolver pointed the open document at.
+    files: HashSet<PathBuf>,
+}
+
+static SCOPE: LazyLock<RwLock<AssetScope>> = LazyLock::new(|| RwLock::new(AssetScope::default()));
+
+impl AssetScope {
+    /// Whether `path`, already canonical, is inside the scope.
+    fn allows(&self, path: &Path, search_paths: &[String]) -> bool {
+        self.files.contains(path)
+            || self
+                .document
+                .as_ref()
+                .and_then(|document| document.parent())
+                .is_some_and(|dir| path.starts_with(dir))
+            || search_paths
+                .iter()
+                .filter_map(|root| fs::canonicalize(root).ok())
+                .any(|root| path.starts_with(root))
+    }
+}
+
+/// Lets rendered content load files below the folder of the document at
+/// `path`, instead of those of the previous document.
+pub fn allow_document(path: &Path) {
+    let Ok(path) = fs::canonicalize(path) else {
+        return;
+    };
+    let mut scope = SCOPE.write();
+    if scope.document.as_ref() != Some(&path) {
+        scope.files.clear();
+        scope.document = Some(path);
+    }
+}
+
+/// Lets rendered content load the file at `path`.
+pub fn allow_file(path: &Path) {
+    if let Ok(path) = fs::canonicalize(path) {
+        SCOPE.write().files.insert(path);
+    }
+}
+
+/// Whether rendered content may load `path`. Symlinks and `..` are resolved
+/// first, so neither can lead out of the scope.
+pub fn is_allowed(path: &Path, search_paths: &[String]) -> bool {
+    fs::canonicalize(path)
+        .map(|path| SCOPE.read().allows(&path, search_paths))
+        .unwrap_or(false)
+}
+
+/// URL the webview loads `path` from through `scheme`, the same one
+/// `convertFileSrc(path, scheme)` builds.
+pub fn scheme_url(scheme: &str, path: &str) -> String {
+    let path = urlencoding::encode(path);
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/{}", scheme, path)
+    } else {
+        format!("{}://localhost/{}", scheme, path)
+    }
+}
+
+/// The file a request is for. The path is one encoded segment, as produced
+/// by `convertFileSrc(path, scheme)`.
+pub fn request_path(request: &Request<Vec<u8>>) -> Option<PathBuf> {
+    let path = urlencoding::decode(request.uri().path().trim_start_matches('/')).ok()?;
+    Some(PathBuf::from(path.as_ref()))
+}
+
+pub fn error_response(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(message.into_bytes())
+        .unwrap_or_default()
+}
+
+/// The bytes a `Range: bytes=...` header asks for in a file of `len` bytes,
+/// or `None` if none of them exist. Only the first of several ranges is
+/// served, and at most `MAX_RANGE_BYTES` of it.
+fn byte_range(header: &str, len: u64) -> Option<Range<u64>> {
+    let spec = header.trim().strip_prefix("bytes=")?.split(',').next()?;
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => (len.saturating_sub(suffix.parse().ok()?), len),
+        (start, "") => (start.parse().ok()?, len),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.saturating_add(1).min(len),
+        ),
+    };
+    (start < end).then(|| start..end.min(start.saturating_add(MAX_RANGE_BYTES)))
+}
+
+fn read_range(path: &Path, range: &Range<u64>) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(range.start))?;
+    let mut bytes = Vec::with_capacity((range.end - range.start) as usize);
+    file.take(range.end - range.start).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Serves the file at `path`, or the part of it `range_header` asks for.
+fn serve_file(path: &Path, range_header: Option<&str>) -> Response<Vec<u8>> {
+    let len = match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => return error_response(StatusCode::NOT_FOUND, "File not found".to_string()),
+    };
+    // Notes, configuration and keys next to a document are not for the webview
+    let Some(mime) = media_mime_type(path) else {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            format!("'{}' is not an image or media file", path.display()),
+        );
+    };
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, "no-cache");
+
+    // A player that does not ask for a range gets the start of a large
+    // recording and seeks for the rest, rather than the whole file at once
+    let streamed = mime.starts_with("audio/") || mime.starts_with("video/");
+    let range_header = range_header.or((streamed && len > MAX_RANGE_BYTES).then_some("bytes=0-"));
+
+    let Some(range_header) = range_header else {
+        return match fs::read(path) {
+            Ok(bytes) => response
+                .header(header::CONTENT_LENGTH, bytes.len())
+                .body(bytes)
+                .unwrap_or_default(),
+            Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        };
+    };
+
+    let Some(range) = byte_range(range_header, len) else {
+        return Response::builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(Vec::new())
+            .unwrap_or_default();
+    };
+    match read_range(path, &range) {
+        Ok(bytes) => response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", range.start, range.end - 1, len),
+            )
+            .header(header::CONTENT_LENGTH, bytes.len())
+            .body(bytes)
+            .unwrap_or_default(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Answers a `readtext://` request, refusing files outside the scope.
+pub fn handle_request(
+    app_handle: &tauri::AppHandle,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let Some(path) = request_path(request) else {
+        return error_response(StatusCode::BAD_REQUEST, "Invalid asset path".to_string());
+    };
+    let search_paths = load_config(app_handle)
+        .map(|config| config.search_paths)
+        .unwrap_or_default();
+    if !is_allowed(&path, &search_paths) {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            format!("'{}' is outside the allowed folders", path.display()),
+        );
+    }
+
+    let range_header = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    serve_file(&path, range_header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+
+    #[test]
+    fn test_byte_range() {
+        assert_eq!(byte_range("bytes=0-99", 1000), Some(0..100));
+        assert_eq!(byte_range("bytes=900-", 1000), Some(900..1000));
+        assert_eq!(byte_range("bytes=-100", 1000), Some(900..1000));
+        assert_eq!(byte_range("bytes=990-2000", 1000), Some(990..1000));
+        assert_eq!(byte_range("bytes=0-1, 5-6", 1000), Some(0..2));
+        assert_eq!(byte_range("bytes=1000-", 1000), None);
+        assert_eq!(byte_range("items=0-1", 1000), None);
+        assert_eq!(
+            byte_range("bytes=0-", 10 * MAX_RANGE_BYTES),
+            Some(0..MAX_RANGE_BYTES)
+        );
+    }
+
+    #[test]
+    fn test_scope() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        fs::create_dir_all(dir.join("notes/img")).unwrap();
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        fs::create_dir_all(dir.join("private")).unwrap();
+        for file in [
+            "notes/img/a.png",
+            "assets/b.png",
+            "private/key",
+            "shared.png",
+        ] {
+            fs::write(dir.join(file), "x").unwrap();
+        }
+        let dir = fs::canonicalize(&dir).unwrap();
+
+        let scope = AssetScope {
+            document: Some(dir.join("notes/plan.md")),
+            files: HashSet::from([dir.join("shared.png")]),
+        };
+        let search_paths = vec![dir.join("assets").to_string_lossy().to_string()];
+        let allows = |file: &str| {
+            let path = fs::canonicalize(dir.join(file)).unwrap();
+            scope.allows(&path, &search_paths)
+        };
+
+        assert!(allows("notes/img/a.png"));
+        assert!(allows("assets/b.png"));
+        assert!(allows("shared.png"));
+        assert!(!allows("private/key"));
+        assert!(!allows("notes/../private/key"));
+
+        let response = serve_file(&dir.join("notes/img/a.png"), Some("bytes=0-0"));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 0-0/1");
+        let response = serve_file(&dir.join("private/key"), None);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_large_media_is_served_in_ranges() {
+        let scratch = scratch_dir();
+        let video = scratch.path().join("talk.mp4");
+        let len = 3 * MAX_RANGE_BYTES;
+        File::create(&video).unwrap().set_len(len).unwrap();
+
+        let response = serve_file(&video, None);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers()[header::CONTENT_RANGE],
+            format!("bytes 0-{}/{}", MAX_RANGE_BYTES - 1, len)
+        );
+        assert_eq!(response.body().len() as u64, MAX_RANGE_BYTES);
+    }
+}
@@ -1,7 +1,32 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+use std::sync::LazyLock;
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+pub const CONFIG_SCHEMA_FILE_NAME: &str = "config.schema.json";
+/// Current `config.json` layout. Bump it together with a new entry in `MIGRATIONS`.
+pub const CONFIG_VERSION: u32 = 1;
+pub const MIN_AUTO_SAVE_DELAY: u32 = 100;
+pub const MAX_AUTO_SAVE_DELAY: u32 = 60_000;
+
+const CSS_LENGTH_PATTERN: &str =
+    r"^(0|none|\d+(\.\d+)?(px|em|rem|%|ch|ex|vw|vh|vmin|vmax|pt|pc|cm|mm|in))$";
+
+static CSS_LENGTH_REGEX: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(CSS_LENGTH_PATTERN).expect("Invalid CSS length regex"));
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct Config {
+    /// Editor hint pointing at the generated `config.schema.json`.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+
+    /// Layout version of the file; older files are upgraded on load.
+    #[serde(default = "default_version")]
+    pub version: u32,
+
     #[serde(default = "default_search_paths")]
     pub search_paths: Vec<String>,
 
@@ -11,11 +36,32 @@ pub struct Config {
     #[serde(default)]
     pub features: Features,
 
+    /// Gitignore-style patterns hidden from the explorer, on top of `.gitignore`.
+    #[serde(default = "default_ignore")]
+    pub ignore: Vec<String>,
+
+    /// Where images are kept: relative to each search path, relative to the
+    /// note when it starts with `./`, or absolute. When several images share a
+    /// name, the one in this folder wins.
+    #[serde(default)]
+    pub attachment_folder: Option<String>,
+
+    #[serde(default)]
+    pub layout: Layout,
+
+    #[serde(default)]
+    pub remote_images: RemoteImages,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct Layout {
+    /// Any CSS length, e.g. `800px`, `70ch` or `100%`.
     #[serde(default = "default_max_width")]
+    #[schemars(regex(pattern = CSS_LENGTH_PATTERN))]
     pub max_width: String,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct InstanceMode {
     #[serde(default)]
     pub enabled: bool,
@@ -24,7 +70,20 @@ pub struct InstanceMode {
     pub allow_multiple_windows: bool,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+/// Offline copies of `https://` images shown in notes.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct RemoteImages {
+    /// Download remote images into the app data folder and show them from there.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Size limit of the cache in megabytes; the least recently shown images go first.
+    #[serde(default = "default_max_cache_mb")]
+    #[schemars(range(min = 1))]
+    pub max_cache_mb: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct Features {
     #[serde(default = "default_true")]
     pub vim_navigation: bool,
@@ -41,21 +100,45 @@ pub struct Features {
     #[serde(default)]
     pub auto_save: bool,
 
+    /// Milliseconds of inactivity before auto-save writes the file.
     #[serde(default = "default_auto_save_delay")]
+    #[schemars(range(min = 100, max = 60000))]
     pub auto_save_delay: u32,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            schema: None,
+            version: default_version(),
             search_paths: default_search_paths(),
             instance_mode: InstanceMode::default(),
             features: Features::default(),
+            ignore: default_ignore(),
+            attachment_folder: None,
+            layout: Layout::default(),
+            remote_images: RemoteImages::default(),
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
             max_width: default_max_width(),
         }
     }
 }
 
+impl Default for RemoteImages {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_cache_mb: default_max_cache_mb(),
+        }
+    }
+}
+
 impl Default for InstanceMode {
     fn default() -> Self {
         Self {
@@ -78,10 +161,21 @@ impl Default for Features {
     }
 }
 
+fn default_version() -> u32 {
+    CONFIG_VERSION
+}
+
 fn default_search_paths() -> Vec<String> {
     vec![]
 }
 
+fn default_ignore() -> Vec<String> {
+    ["node_modules", "target", "dist", "build"]
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
 fn default_true() -> bool {
     true
 }
@@ -90,10 +184,365 @@ fn default_auto_save_delay() -> u32 {
     1000
 }
 
+fn default_max_cache_mb() -> u64 {
+    200
+}
+
 fn default_max_width() -> String {
     "800px".to_string()
 }
 
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    /// The value was rejected and the default is used instead.
+    Error,
+    /// The value is kept but probably not what the user wants.
+    Warning,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// Dotted path of the offending setting, empty for file-level problems.
+    pub field: String,
+    pub message: String,
+    pub severity: IssueSeverity,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+        if !self.field.is_empty() {
+            write!(f, "{}: ", self.field)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ConfigReport {
+    pub config: Config,
+    pub issues: Vec<ConfigIssue>,
+    /// Set when the file could not be used at all and `config` is only the
+    /// defaults, so a reload can keep the settings already in effect.
+    #[serde(skip)]
+    pub unusable: bool,
+}
+
+/// Parses `config.json`, replacing every invalid setting with its default
+/// instead of rejecting the whole file. Older files are checked the way they
+/// load, upgraded to the current layout, and issues point into that text.
+pub fn parse_config(source: &str) -> ConfigReport {
+    let upgraded = upgrade_config_source(source);
+    let source = upgraded.as_ref().map_or(source, |(_, text)| text.as_str());
+    let mut issues = Vec::new();
+
+    let user_value: Value = match serde_json::from_str(source) {
+        Ok(value) => value,
+        Err(e) => {
+            // serde_json appends " at line X column Y"; we report those separately
+            let reason = e.to_string();
+            let reason = reason.split(" at line ").next().unwrap_or_default();
+            issues.push(ConfigIssue {
+                field: String::new(),
+                message: format!("Invalid JSON ({}); the file is ignored", reason),
+                severity: IssueSeverity::Error,
+                line: Some(e.line()),
+                column: Some(e.column()),
+            });
+            return ConfigReport {
+                config: Config::default(),
+                issues,
+                unusable: true,
+            };
+        }
+    };
+
+    if let Some(version) = user_value.get("version").and_then(Value::as_u64) {
+        if version > CONFIG_VERSION as u64 {
+            issues.push(issue_at(
+                "version",
+                format!(
+                    "Written by a newer readtext (version {}, this build understands {}); unknown settings are ignored",
+                    version, CONFIG_VERSION
+                ),
+                IssueSeverity::Warning,
+                locate_field(source, &["version".to_string()]),
+            ));
+        }
+    }
+
+    if !user_value.is_object() {
+        issues.push(ConfigIssue {
+            field: String::new(),
+            message: "Expected a JSON object; the file is ignored".to_string(),
+            severity: IssueSeverity::Error,
+            line: Some(1),
+            column: Some(1),
+        });
+        return ConfigReport {
+            config: Config::default(),
+            issues,
+            unusable: true,
+        };
+    }
+
+    let mut merged = serde_json::to_value(Config::default()).expect("Default config serializes");
+    let mut assignments = Vec::new();
+    collect_assignments(&merged, &user_value, &mut Vec::new(), &mut assignments);
+
+    for (path, value) in assignments {
+        let field = path.join(".");
+        let location = locate_field(source, &path);
+
+        let Some(value) = value else {
+            issues.push(issue_at(
+                &field,
+                "Unknown setting; it will be ignored".to_string(),
+                IssueSeverity::Warning,
+                location,
+            ));
+            continue;
+        };
+
+        let mut candidate = merged.clone();
+        set_path(&mut candidate, &path, value);
+        match serde_json::from_value::<Config>(candidate.clone()) {
+            Ok(_) => merged = candidate,
+            Err(e) => issues.push(issue_at(
+                &field,
+                format!("{}; using default", e),
+                IssueSeverity::Error,
+                location,
+            )),
+        }
+    }
+
+    let mut config: Config = serde_json::from_value(merged).unwrap_or_default();
+    validate_values(&mut config, source, &mut issues);
+    issues.sort_by_key(|issue| (issue.line.unwrap_or(usize::MAX), issue.column));
+
+    ConfigReport {
+        config,
+        issues,
+        unusable: false,
+    }
+}
+
+type Migration = fn(&mut serde_json::Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades a version `n` file to version `n + 1`.
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Files written before `version` existed kept `max_width` at the top level.
+fn migrate_v0_to_v1(config: &mut serde_json::Map<String, Value>) {
+    let Some(max_width) = config.remove("max_width") else {
+        return;
+    };
+    let layout = config
+        .entry("layout")
+        .or_insert_with(|| Value::Object(serde_json::Map::new()));
+    if let Some(layout) = layout.as_object_mut() {
+        layout.entry("max_width").or_insert(max_width);
+        return;
+    }
+    // Left where it was, to be reported rather than lost
+    config.insert("max_width".to_string(), max_width);
+}
+
+/// Runs every pending migration on a parsed `config.json` and returns the
+/// version it started from, or `None` if it was already current.
+pub fn migrate_config_value(value: &mut Value) -> Option<u32> {
+    let config = value.as_object_mut()?;
+    let from = match config.get("version") {
+        None => 0,
+        Some(v) => v.as_u64().and_then(|v| u32::try_from(v).ok())?,
+    };
+
+    if from >= CONFIG_VERSION {
+        return None;
+    }
+
+    for migration in &MIGRATIONS[from as usize..] {
+        migration(config);
+    }
+    config.insert("version".to_string(), Value::from(CONFIG_VERSION));
+
+    Some(from)
+}
+
+/// Upgrades raw `config.json` text. Returns the old version and the rewritten
+/// text, keeping values as the user wrote them (even invalid ones) so nothing
+/// is lost when the file is replaced.
+pub fn upgrade_config_source(source: &str) -> Option<(u32, String)> {
+    let mut value: Value = serde_json::from_str(source).ok()?;
+    let from = migrate_config_value(&mut value)?;
+    let upgraded = serde_json::to_string_pretty(&value).ok()?;
+    Some((from, upgraded))
+}
+
+fn issue_at(
+    field: &str,
+    message: String,
+    severity: IssueSeverity,
+    location: Option<(usize, usize)>,
+) -> ConfigIssue {
+    ConfigIssue {
+        field: field.to_string(),
+        message,
+        severity,
+        line: location.map(|(line, _)| line),
+        column: location.map(|(_, column)| column),
+    }
+}
+
+/// Flattens the user's JSON into leaf assignments, descending only where the
+/// default config also has an object. `None` marks a key `Config` doesn't know.
+fn collect_assignments(
+    defaults: &Value,
+    user: &Value,
+    path: &mut Vec<String>,
+    out: &mut Vec<(Vec<String>, Option<Value>)>,
+) {
+    let (Some(default_map), Some(user_map)) = (defaults.as_object(), user.as_object()) else {
+        out.push((path.clone(), Some(user.clone())));
+        return;
+    };
+
+    for (key, value) in user_map {
+        path.push(key.clone());
+        match default_map.get(key) {
+            Some(default_value) => collect_assignments(default_value, value, path, out),
+            None if key == "$schema" && path.len() == 1 => {
+                out.push((path.clone(), Some(value.clone())))
+            }
+            None => out.push((path.clone(), None)),
+        }
+        path.pop();
+    }
+}
+
+fn set_path(root: &mut Value, path: &[String], value: Value) {
+    let mut node = root;
+    for key in &path[..path.len() - 1] {
+        node = &mut node[key.as_str()];
+    }
+    node[path[path.len() - 1].as_str()] = value;
+}
+
+fn validate_values(config: &mut Config, source: &str, issues: &mut Vec<ConfigIssue>) {
+    for path in &config.search_paths {
+        if !Path::new(path).is_dir() {
+            issues.push(issue_at(
+                "search_paths",
+                format!("'{}' does not exist or is not a directory", path),
+                IssueSeverity::Warning,
+                locate_field(source, &["search_paths".to_string()]),
+            ));
+        }
+    }
+
+    let mut invalid_patterns = Vec::new();
+    for pattern in &config.ignore {
+        if let Err(e) = ignore::overrides::OverrideBuilder::new("/").add(pattern) {
+            issues.push(issue_at(
+                "ignore",
+                format!("'{}' is not a valid pattern ({}); skipping it", pattern, e),
+                IssueSeverity::Error,
+                locate_field(source, &["ignore".to_string()]),
+            ));
+            invalid_patterns.push(pattern.clone());
+        }
+    }
+    config.ignore.retain(|p| !invalid_patterns.contains(p));
+
+    if !is_valid_css_length(&config.layout.max_width) {
+        issues.push(issue_at(
+            "layout.max_width",
+            format!(
+                "'{}' is not a CSS length (e.g. 800px, 70ch, 100%); using {}",
+                config.layout.max_width,
+                default_max_width()
+            ),
+            IssueSeverity::Error,
+            locate_field(source, &["layout".to_string(), "max_width".to_string()]),
+        ));
+        config.layout.max_width = default_max_width();
+    }
+
+    if config.remote_images.max_cache_mb == 0 {
+        issues.push(issue_at(
+            "remote_images.max_cache_mb",
+            format!(
+                "The cache needs at least 1 MB; using {}",
+                default_max_cache_mb()
+            ),
+            IssueSeverity::Error,
+            locate_field(
+                source,
+                &["remote_images".to_string(), "max_cache_mb".to_string()],
+            ),
+        ));
+        config.remote_images.max_cache_mb = default_max_cache_mb();
+    }
+
+    let delay = config.features.auto_save_delay;
+    if !(MIN_AUTO_SAVE_DELAY..=MAX_AUTO_SAVE_DELAY).contains(&delay) {
+        issues.push(issue_at(
+            "features.auto_save_delay",
+            format!(
+                "{} ms is outside {}..={} ms; using {}",
+                delay,
+                MIN_AUTO_SAVE_DELAY,
+                MAX_AUTO_SAVE_DELAY,
+                default_auto_save_delay()
+            ),
+            IssueSeverity::Error,
+            locate_field(
+                source,
+                &["features".to_string(), "auto_save_delay".to_string()],
+            ),
+        ));
+        config.features.auto_save_delay = default_auto_save_delay();
+    }
+}
+
+pub fn is_valid_css_length(value: &str) -> bool {
+    CSS_LENGTH_REGEX.is_match(value.trim())
+}
+
+/// Best-effort 1-based line/column of a (possibly nested) key in the raw JSON.
+fn locate_field(source: &str, path: &[String]) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    for key in path {
+        let needle = format!("\"{}\"", key);
+        let mut search_from = offset;
+        loop {
+            let found = search_from + source[search_from..].find(&needle)?;
+            let after = source[found + needle.len()..].trim_start();
+            if after.starts_with(':') {
+                offset = found;
+                break;
+            }
+            search_from = found + needle.len();
+        }
+    }
+
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = source[line_start..offset].chars().count() + 1;
+    Some((line, column))
+}
+
+pub fn config_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(Config)).expect("Config schema serializes")
+}
+
 pub fn set_default_env() {
     #[cfg(target_os = "linux")]
     unsafe {
@@ -110,7 +559,8 @@ mod tests {
     fn test_config_default() {
         let config = Config::default();
         assert!(config.search_paths.is_empty());
-        assert_eq!(config.max_width, "800px");
+        assert_eq!(config.layout.max_width, "800px");
+        assert!(!config.remote_images.enabled);
     }
 
     #[test]
@@ -134,6 +584,8 @@ mod tests {
     #[test]
     fn test_config_serialization() {
         let config = Config {
+            schema: None,
+            version: CONFIG_VERSION,
             search_paths: vec!["/tmp/test".to_string()],
             instance_mode: InstanceMode {
                 enabled: true,
@@ -147,7 +599,15 @@ mod tests {
                 auto_save: true,
                 auto_save_delay: 500,
             },
-            max_width: "100%".to_string(),
+            ignore: vec!["drafts/".to_string()],
+            attachment_folder: Some("attachments".to_string()),
+            layout: Layout {
+                max_width: "100%".to_string(),
+            },
+            remote_images: RemoteImages {
+                enabled: true,
+                max_cache_mb: 50,
+            },
         };
 
         let json = serde_json::to_string(&config).unwrap();
@@ -157,6 +617,183 @@ mod tests {
         assert_eq!(deserialized.instance_mode.enabled, true);
         assert_eq!(deserialized.features.vim_mode, true);
         assert_eq!(deserialized.features.auto_save_delay, 500);
-        assert_eq!(deserialized.max_width, "100%");
+        assert_eq!(deserialized.ignore, vec!["drafts/".to_string()]);
+        assert_eq!(
+            deserialized.attachment_folder.as_deref(),
+            Some("attachments")
+        );
+        assert_eq!(deserialized.layout.max_width, "100%");
+        assert!(deserialized.remote_images.enabled);
+        assert_eq!(deserialized.remote_images.max_cache_mb, 50);
+    }
+
+    #[test]
+    fn test_parse_config_valid() {
+        let report = parse_config(
+            r#"{ "version": 1, "layout": { "max_width": "70ch" }, "features": { "auto_save": true } }"#,
+        );
+        assert!(report.issues.is_empty());
+        assert_eq!(report.config.layout.max_width, "70ch");
+        assert!(report.config.features.auto_save);
+        assert!(report.config.features.live_reload);
+    }
+
+    #[test]
+    fn test_parse_config_syntax_error_reports_location() {
+        let report = parse_config("{\n  \"max_width\": \"800px\",\n}");
+        assert_eq!(report.config, Config::default());
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].severity, IssueSeverity::Error);
+        assert_eq!(report.issues[0].line, Some(3));
+        assert!(report.unusable);
+    }
+
+    #[test]
+    fn test_parse_config_falls_back_per_field() {
+        let source = "{\n  \"version\": 1,\n  \"layout\": {\n    \"max_width\": 42\n  },\n  \"features\": {\n    \"vim_mode\": true,\n    \"auto_save_delay\": \"soon\"\n  }\n}";
+        let report = parse_config(source);
+
+        assert_eq!(report.config.layout.max_width, "800px");
+        assert!(report.config.features.vim_mode);
+        assert_eq!(report.config.features.auto_save_delay, 1000);
+
+        let fields: Vec<_> = report.issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(fields, vec!["layout.max_width", "features.auto_save_delay"]);
+        assert_eq!(report.issues[0].line, Some(4));
+        assert_eq!(report.issues[0].column, Some(5));
+        assert_eq!(report.issues[1].line, Some(8));
+    }
+
+    #[test]
+    fn test_parse_config_semantic_checks() {
+        let source = r#"{
+            "version": 1,
+            "search_paths": ["/definitely/not/here"],
+            "layout": { "max_width": "wide" },
+            "features": { "auto_save_delay": 5 }
+        }"#;
+        let report = parse_config(source);
+
+        assert_eq!(report.config.search_paths, vec!["/definitely/not/here"]);
+        assert_eq!(report.config.layout.max_width, "800px");
+        assert_eq!(report.config.features.auto_save_delay, 1000);
+        assert_eq!(report.issues.len(), 3);
+        assert_eq!(report.issues[0].severity, IssueSeverity::Warning);
+        assert_eq!(report.issues[1].severity, IssueSeverity::Error);
+    }
+
+    #[test]
+    fn test_parse_config_unknown_keys() {
+        let report = parse_config(
+            r#"{ "$schema": "./config.schema.json", "version": 1, "max_widht": "90%" }"#,
+        );
+        assert_eq!(
+            report.config.schema.as_deref(),
+            Some("./config.schema.json")
+        );
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].field, "max_widht");
+        assert_eq!(report.issues[0].severity, IssueSeverity::Warning);
+    }
+
+    #[test]
+    fn test_css_length() {
+        for ok in ["800px", "70ch", "100%", "42.5rem", "0", "none"] {
+            assert!(is_valid_css_length(ok), "{}", ok);
+        }
+        for bad in ["", "800", "wide", "px", "-1px", "80 px"] {
+            assert!(!is_valid_css_length(bad), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_config_schema_has_bounds() {
+        let schema = config_schema();
+        let delay = &schema["$defs"]["Features"]["properties"]["auto_save_delay"];
+        assert_eq!(delay["minimum"], 100);
+        assert_eq!(delay["maximum"], 60000);
+    }
+
+    #[test]
+    fn test_config_default_version() {
+        assert_eq!(Config::default().version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_unversioned_readme_fixture() {
+        let source = include_str!("../tests/fixtures/config/v0_readme.json");
+        let (from, upgraded) = upgrade_config_source(source).expect("v0 needs migration");
+        assert_eq!(from, 0);
+
+        let report = parse_config(&upgraded);
+        assert_eq!(report.config.version, CONFIG_VERSION);
+        assert_eq!(
+            report.config.search_paths,
+            vec!["/home/mintori/Documents/[2] Obsidian/"]
+        );
+        assert!(report.config.instance_mode.enabled);
+        assert!(report.config.features.vim_navigation);
+        assert!(report.config.features.auto_index);
+
+        // Already current: nothing to do
+        assert!(upgrade_config_source(&upgraded).is_none());
+    }
+
+    #[test]
+    fn test_migrate_keeps_invalid_values_for_the_user() {
+        let source = include_str!("../tests/fixtures/config/v0_invalid_values.json");
+        let (_, upgraded) = upgrade_config_source(source).unwrap();
+        let value: Value = serde_json::from_str(&upgraded).unwrap();
+
+        assert_eq!(value["layout"]["max_width"], "wide");
+        assert!(value.get("max_width").is_none());
+        assert_eq!(value["features"]["auto_save_delay"], 5);
+        assert_eq!(value["version"], CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_current_fixture_is_noop() {
+        let source = include_str!("../tests/fixtures/config/v1_current.json");
+        assert!(upgrade_config_source(source).is_none());
+
+        let report = parse_config(source);
+        assert!(report.issues.is_empty());
+        assert_eq!(report.config.layout.max_width, "70ch");
+        assert_eq!(report.config.features.auto_save_delay, 2000);
+    }
+
+    #[test]
+    fn test_newer_version_is_not_downgraded() {
+        let source = include_str!("../tests/fixtures/config/v99_future.json");
+        assert!(upgrade_config_source(source).is_none());
+
+        let report = parse_config(source);
+        assert_eq!(report.config.version, 99);
+        assert_eq!(report.config.layout.max_width, "900px");
+        let fields: Vec<_> = report.issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(fields, vec!["version", "layout.columns"]);
+    }
+
+    #[test]
+    fn test_migrate_moves_max_width_under_layout() {
+        let source = include_str!("../tests/fixtures/config/v0_invalid_values.json");
+        let report = parse_config(source);
+
+        // Checked where it now lives, in the upgraded text
+        let issue = report
+            .issues
+            .iter()
+            .find(|issue| issue.field == "layout.max_width")
+            .expect("max_width is still validated");
+        assert!(issue.line.is_some());
+        assert_eq!(report.config.layout.max_width, "800px");
+
+        // A layout section written by hand wins over the old setting
+        let mut value: Value =
+            serde_json::from_str(r#"{ "max_width": "700px", "layout": { "max_width": "60ch" } }"#)
+                .unwrap();
+        assert_eq!(migrate_config_value(&mut value), Some(0));
+        assert_eq!(value["layout"]["max_width"], "60ch");
+        assert!(value.get("max_width").is_none());
     }
 }
@@ -0,0 +1,139 @@
+use crate::config::{Config, ConfigIssue};
+use crate::helper::{cached_config, get_path, load_config, reload_config};
+use crate::image_cache::{index_search_paths, DatabaseState};
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const CONFIG_CHANGED_EVENT: &str = "config-changed";
+
+const CONFIG_FILE_NAME: &str = "config.json";
+const USER_CSS_FILE_NAME: &str = "style.css";
+
+#[derive(Serialize, Clone)]
+pub struct ConfigChanged {
+    pub config: Config,
+    pub user_css: String,
+    /// Problems found while validating; invalid fields were replaced by defaults.
+    pub issues: Vec<ConfigIssue>,
+    /// Set when `config.json` could not be read; `config` is then the last good one.
+    pub error: Option<String>,
+}
+
+fn read_user_css(app_handle: &AppHandle) -> String {
+    std::fs::read_to_string(get_path(app_handle, USER_CSS_FILE_NAME)).unwrap_or_default()
+}
+
+fn is_watched_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n == CONFIG_FILE_NAME || n == USER_CSS_FILE_NAME)
+        .unwrap_or(false)
+}
+
+/// Indexes the search paths again, off the watcher thread, once the index
+/// has opened.
+fn reindex(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(state) = app_handle.try_state::<DatabaseState>() else {
+            return;
+        };
+        let mut connection = state.0.lock().unwrap();
+        if let Err(e) = index_search_paths(&app_handle, &mut connection) {
+            eprintln!("Indexing error: {}", e);
+        }
+    });
+}
+
+/// Watches `config.json` and `style.css` in the app config directory and
+/// broadcasts `config-changed` to every window when either one changes on disk.
+/// The image index is rebuilt once when the search paths change.
+pub fn start_config_watch(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        let config_dir = match get_path(&app_handle, CONFIG_FILE_NAME).parent() {
+            Some(dir) => dir.to_path_buf(),
+            None => return,
+        };
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = match notify::RecommendedWatcher::new(tx, notify::Config::default()) {
+            Ok(w) => w,
+            Err(e) => {
+                eprintln!("Failed to create config watcher: {:?}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = watcher.watch(&config_dir, RecursiveMode::NonRecursive) {
+            eprintln!("Failed to watch config directory: {:?}", e);
+            return;
+        }
+
+        let mut last_config = cached_config().or_else(|| load_config(&app_handle).ok());
+        let mut last_css = read_user_css(&app_handle);
+        let mut last_issues = Vec::new();
+
+        loop {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(Ok(event)) => {
+                    let relevant = (event.kind.is_modify() || event.kind.is_create())
+                        && event.paths.iter().any(|p| is_watched_file(p));
+                    if !relevant {
+                        continue;
+                    }
+
+                    // Editors often save in several steps; let them settle and
+                    // drop the burst of events that follows.
+                    std::thread::sleep(Duration::from_millis(100));
+                    while rx.try_recv().is_ok() {}
+
+                    let (config, issues, error) = match reload_config(&app_handle) {
+                        Ok(report) => (report.config, report.issues, None),
+                        Err(e) => {
+                            let cfg = last_config.clone().unwrap_or_default();
+                            (cfg, Vec::new(), Some(e))
+                        }
+                    };
+                    let user_css = read_user_css(&app_handle);
+
+                    let unchanged = error.is_none()
+                        && last_config.as_ref() == Some(&config)
+                        && last_css == user_css
+                        && last_issues == issues;
+                    if unchanged {
+                        continue;
+                    }
+
+                    let search_paths_changed = error.is_none()
+                        && last_config
+                            .as_ref()
+                            .is_none_or(|last| last.search_paths != config.search_paths);
+                    if search_paths_changed {
+                        reindex(&app_handle);
+                    }
+
+                    last_config = Some(config.clone());
+                    last_css = user_css.clone();
+                    last_issues = issues.clone();
+
+                    let payload = ConfigChanged {
+                        config,
+                        user_css,
+                        issues,
+                        error,
+                    };
+                    if let Err(e) = app_handle.emit(CONFIG_CHANGED_EVENT, payload) {
+                        eprintln!("Failed to emit config change: {:?}", e);
+                    }
+                }
+                Ok(Err(e)) => eprintln!("config watch error: {:?}", e),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// What the frontend knew about a file when it loaded it. Passed back to
+/// `save_file` so a save can be rejected if the file changed in between.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileStamp {
+    pub modified_ms: u64,
+    pub len: u64,
+    pub hash: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SaveOutcome {
+    Saved {
+        stamp: FileStamp,
+    },
+    /// The file on disk no longer matches the expected stamp; nothing was written.
+    Conflict {
+        current: FileStamp,
+    },
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn modified_ms(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .unwrap_or(UNIX_EPOCH)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn stamp_bytes(bytes: &[u8], metadata: &fs::Metadata) -> FileStamp {
+    FileStamp {
+        modified_ms: modified_ms(metadata),
+        len: bytes.len() as u64,
+        hash: content_hash(bytes),
+    }
+}
+
+pub fn stamp_of(path: &Path) -> io::Result<FileStamp> {
+    let bytes = fs::read(path)?;
+    Ok(stamp_bytes(&bytes, &fs::metadata(path)?))
+}
+
+/// Reads `path` along with the stamp of exactly the bytes read, so a write
+/// that lands right after cannot become the baseline of the next save.
+pub fn read_stamped(path: &Path) -> io::Result<(String, FileStamp)> {
+    let bytes = fs::read(path)?;
+    let stamp = stamp_bytes(&bytes, &fs::metadata(path)?);
+    let content =
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok((content, stamp))
+}
+
+/// Writes `bytes` to a temp file next to `path` and renames it into place, so
+/// a crash leaves either the old or the new content, never a truncated file.
+/// Symlinks are followed and the original permissions are kept.
+pub fn atomic_write(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let target: PathBuf = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = target
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no parent"))?;
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+
+    let tmp_path = dir.join(format!(
+        ".{}.readtext-{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+
+        if let Ok(metadata) = fs::metadata(&target) {
+            fs::set_permissions(&tmp_path, metadata.permissions())?;
+        }
+
+        fs::rename(&tmp_path, &target)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Saves `content` to `path` unless the file changed since `expected` was taken.
+/// Earlier versions are kept by `History`, which the caller records into.
+pub fn save_checked(
+    path: &Path,
+    content: &str,
+    expected: Option<&FileStamp>,
+) -> Result<SaveOutcome, String> {
+    let previous = match fs::read(path) {
+        Ok(bytes) => Some(bytes),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read file before saving: {}", e)),
+    };
+
+    if let (Some(expected), Some(previous)) = (expected, &previous) {
+        let current_hash = content_hash(previous);
+        if current_hash != expected.hash {
+            let current = stamp_of(path).map_err(|e| e.to_string())?;
+            return Ok(SaveOutcome::Conflict { current });
+        }
+    }
+
+    if previous.as_deref() == Some(content.as_bytes()) {
+        let stamp = stamp_of(path).map_err(|e| e.to_string())?;
+        return Ok(SaveOutcome::Saved { stamp });
+    }
+
+    atomic_write(path, content.as_bytes()).map_err(|e| format!("Failed to save file: {}", e))?;
+    let stamp = stamp_of(path).map_err(|e| e.to_string())?;
+    Ok(SaveOutcome::Saved { stamp })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+
+    #[test]
+    fn test_atomic_write_replaces_content_and_keeps_permissions() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let path = dir.join("note.md");
+        fs::write(&path, "old").unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        atomic_write(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+
+        let leftovers: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(leftovers.len(), 1);
+    }
+
+    #[test]
+    fn test_save_rejects_stale_stamp() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let path = dir.join("note.md");
+        fs::write(&path, "loaded").unwrap();
+        let stamp = stamp_of(&path).unwrap();
+
+        fs::write(&path, "edited elsewhere").unwrap();
+
+        let outcome = save_checked(&path, "mine", Some(&stamp)).unwrap();
+        assert!(matches!(outcome, SaveOutcome::Conflict { .. }));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "edited elsewhere");
+
+        // The stamp read with the content is the baseline for that content
+        let (content, stamp) = read_stamped(&path).unwrap();
+        assert_eq!(content, "edited elsewhere");
+        assert_eq!(stamp, stamp_of(&path).unwrap());
+
+        let outcome = save_checked(&path, "mine", None).unwrap();
+        assert!(matches!(outcome, SaveOutcome::Saved { .. }));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "mine");
+    }
+}
@@ -0,0 +1,388 @@
+use crate::helper::load_config;
+use crate::image_cache::is_media_file;
+use crate::links::NOTE_EXTENSIONS;
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::WalkBuilder;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+
+/// Streamed entries are flushed at least this often while a large tree is walked.
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Folder,
+    Note,
+    Asset,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TreeEntry {
+    pub name: String,
+    pub path: String,
+    /// Folder this entry is listed under; the requested folder for the first level.
+    pub parent: String,
+    pub kind: EntryKind,
+    /// Notes directly in this folder (1 for a note, 0 for an asset).
+    pub note_count: usize,
+}
+
+fn classify(path: &Path) -> Option<EntryKind> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    if NOTE_EXTENSIONS.contains(&ext.as_str()) {
+        Some(EntryKind::Note)
+    } else if is_media_file(path) {
+        Some(EntryKind::Asset)
+    } else {
+        None
+    }
+}
+
+/// Compares names the way people expect: case-insensitive, with runs of
+/// digits compared by value, so `note 2` sorts before `note 10`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+                        digits.push(c);
+                        chars.next();
+                    }
+                    digits
+                };
+                let x_num = take_number(&mut a_chars);
+                let y_num = take_number(&mut b_chars);
+                let x_trimmed = x_num.trim_start_matches('0');
+                let y_trimmed = y_num.trim_start_matches('0');
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn build_overrides(root: &Path, patterns: &[String]) -> Override {
+    let mut builder = OverrideBuilder::new(root);
+    for pattern in patterns {
+        // Overrides whitelist by default; `!` turns the pattern into an ignore rule
+        if let Err(e) = builder.add(&format!("!{}", pattern)) {
+            eprintln!("Skipping ignore pattern '{}': {}", pattern, e);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Override::empty())
+}
+
+struct OpenFolder {
+    entry: TreeEntry,
+    path: PathBuf,
+    /// Assets and listed subfolders directly inside, besides its notes.
+    others: usize,
+}
+
+/// Folders are only final once the walk has left them. Those with nothing
+/// listed in them are dropped.
+fn close_folder(folder: OpenFolder, open: &mut [OpenFolder], emit: &mut impl FnMut(TreeEntry)) {
+    if folder.entry.note_count == 0 && folder.others == 0 {
+        return;
+    }
+    if let Some(parent) = open.last_mut() {
+        parent.others += 1;
+    }
+    emit(folder.entry);
+}
+
+fn walk(dir: &Path, overrides: &Override, max_depth: usize) -> ignore::Walk {
+    WalkBuilder::new(dir)
+        .require_git(false)
+        .overrides(overrides.clone())
+        .max_depth(Some(max_depth))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build()
+}
+
+fn kind_of(dir_entry: &ignore::DirEntry) -> Option<EntryKind> {
+    if dir_entry.file_type().is_some_and(|t| t.is_dir()) {
+        Some(EntryKind::Folder)
+    } else {
+        classify(dir_entry.path())
+    }
+}
+
+/// Notes and other entries directly in `dir`, read without listing them, for
+/// a folder at the deepest level requested.
+fn direct_counts(dir: &Path, overrides: &Override) -> (usize, usize) {
+    let mut counts = (0, 0);
+    for dir_entry in walk(dir, overrides, 1).filter_map(Result::ok) {
+        match (dir_entry.depth(), kind_of(&dir_entry)) {
+            (0, _) | (_, None) => {}
+            (_, Some(EntryKind::Note)) => counts.0 += 1,
+            (_, Some(_)) => counts.1 += 1,
+        }
+    }
+    counts
+}
+
+/// Lists notes, assets and the folders containing them under `root`, down to
+/// `max_depth` levels. `.gitignore`, hidden files and `ignore` patterns are
+/// skipped. Nothing below `max_depth` is walked: folders on the last level
+/// are counted from a single read of their contents. Entries are handed to
+/// `on_entry` as soon as they are final, and the returned list is in display
+/// order: folders first, then files, each sorted naturally.
+pub fn list_tree_at(
+    root: &Path,
+    ignore: &[String],
+    max_depth: usize,
+    mut on_entry: impl FnMut(&TreeEntry),
+) -> Result<Vec<TreeEntry>, String> {
+    if !root.is_dir() {
+        return Err(format!("'{}' is not a directory", root.display()));
+    }
+
+    // Drop trailing separators so `parent` of first-level entries equals `root`
+    let root: PathBuf = root.components().collect();
+    let overrides = build_overrides(&root, ignore);
+
+    let mut entries = Vec::new();
+    let mut emit = |entry: TreeEntry| {
+        on_entry(&entry);
+        entries.push(entry);
+    };
+    let mut open: Vec<OpenFolder> = Vec::new();
+
+    for result in walk(&root, &overrides, max_depth) {
+        let dir_entry = match result {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Skipping unreadable entry: {}", e);
+                continue;
+            }
+        };
+        if dir_entry.depth() == 0 {
+            continue;
+        }
+
+        let path = dir_entry.path();
+        while let Some(folder) = open.pop() {
+            if path.starts_with(&folder.path) {
+                open.push(folder);
+                break;
+            }
+            close_folder(folder, &mut open, &mut emit);
+        }
+
+        let Some(kind) = kind_of(&dir_entry) else {
+            continue;
+        };
+        let mut entry = TreeEntry {
+            name: dir_entry.file_name().to_string_lossy().into_owned(),
+            path: path.to_string_lossy().into_owned(),
+            parent: path
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            kind,
+            note_count: usize::from(kind == EntryKind::Note),
+        };
+
+        match kind {
+            EntryKind::Folder => {
+                // The walk fills in the counts of folders it descends into
+                let (notes, others) = if dir_entry.depth() == max_depth {
+                    direct_counts(path, &overrides)
+                } else {
+                    (0, 0)
+                };
+                entry.note_count = notes;
+                open.push(OpenFolder {
+                    entry,
+                    path: path.to_path_buf(),
+                    others,
+                });
+            }
+            EntryKind::Note | EntryKind::Asset => {
+                if let Some(parent) = open.last_mut() {
+                    parent.entry.note_count += entry.note_count;
+                    parent.others += usize::from(kind == EntryKind::Asset);
+                }
+                emit(entry);
+            }
+        }
+    }
+
+    while let Some(folder) = open.pop() {
+        close_folder(folder, &mut open, &mut emit);
+    }
+
+    Ok(in_display_order(entries, &root.to_string_lossy()))
+}
+
+fn in_display_order(entries: Vec<TreeEntry>, root: &str) -> Vec<TreeEntry> {
+    let mut by_parent: HashMap<String, Vec<TreeEntry>> = HashMap::new();
+    for entry in entries {
+        by_parent
+            .entry(entry.parent.clone())
+            .or_default()
+            .push(entry);
+    }
+
+    fn push_children(
+        parent: &str,
+        by_parent: &mut HashMap<String, Vec<TreeEntry>>,
+        out: &mut Vec<TreeEntry>,
+    ) {
+        let Some(mut siblings) = by_parent.remove(parent) else {
+            return;
+        };
+        siblings.sort_by(|a, b| {
+            let a_folder = a.kind == EntryKind::Folder;
+            let b_folder = b.kind == EntryKind::Folder;
+            b_folder
+                .cmp(&a_folder)
+                .then_with(|| natural_cmp(&a.name, &b.name))
+        });
+        for entry in siblings {
+            let path = entry.path.clone();
+            out.push(entry);
+            push_children(&path, by_parent, out);
+        }
+    }
+
+    let mut ordered = Vec::new();
+    push_children(root, &mut by_parent, &mut ordered);
+    ordered
+}
+
+/// Lists `path` for the explorer. Only `depth` levels are returned (default 1)
+/// so deeper folders can be loaded when expanded. When `on_batch` is given,
+/// entries are also streamed in batches while the walk is still running.
+#[tauri::command]
+pub async fn list_tree(
+    app_handle: tauri::AppHandle,
+    path: String,
+    depth: Option<usize>,
+    on_batch: Option<Channel<Vec<TreeEntry>>>,
+) -> Result<Vec<TreeEntry>, String> {
+    let ignore = load_config(&app_handle)?.ignore;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut batch = Vec::new();
+        let mut last_flush = Instant::now();
+
+        let entries = list_tree_at(Path::new(&path), &ignore, depth.unwrap_or(1), |entry| {
+            let Some(channel) = &on_batch else {
+                return;
+            };
+            batch.push(entry.clone());
+            if last_flush.elapsed() >= BATCH_INTERVAL {
+                let _ = channel.send(std::mem::take(&mut batch));
+                last_flush = Instant::now();
+            }
+        })?;
+
+        if let (Some(channel), false) = (&on_batch, batch.is_empty()) {
+            let _ = channel.send(batch);
+        }
+        Ok(entries)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+    use std::fs;
+
+    fn names(entries: &[TreeEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec![
+            "note 10.md",
+            "Note 2.md",
+            "note 1.md",
+            "apple.md",
+            "note 02b.md",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "apple.md",
+                "note 1.md",
+                "Note 2.md",
+                "note 02b.md",
+                "note 10.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_tree_filters_and_counts() {
+        let scratch = scratch_dir();
+        let root = scratch.path().to_path_buf();
+        fs::create_dir_all(root.join("docs/deep")).unwrap();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::create_dir_all(root.join("out")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "out/\n").unwrap();
+        fs::write(root.join("readme.md"), "").unwrap();
+        fs::write(root.join("script.js"), "").unwrap();
+        fs::write(root.join("docs/a.md"), "").unwrap();
+        fs::write(root.join("docs/deep/b.md"), "").unwrap();
+        fs::write(root.join("docs/deep/c.png"), "").unwrap();
+        fs::write(root.join("node_modules/pkg/README.md"), "").unwrap();
+        fs::write(root.join("out/generated.md"), "").unwrap();
+
+        let ignore = vec!["node_modules".to_string()];
+        let mut streamed = 0;
+        let entries = list_tree_at(&root, &ignore, 1, |_| streamed += 1).unwrap();
+
+        assert_eq!(names(&entries), vec!["docs", "readme.md"]);
+        assert_eq!(entries[0].kind, EntryKind::Folder);
+        assert_eq!(entries[0].note_count, 1);
+        assert_eq!(streamed, entries.len());
+
+        let all = list_tree_at(&root, &ignore, usize::MAX, |_| {}).unwrap();
+        assert_eq!(
+            names(&all),
+            vec!["docs", "deep", "b.md", "c.png", "a.md", "readme.md"]
+        );
+        assert_eq!(all[1].note_count, 1);
+
+        // Folders on the last level are counted, not listed
+        let deep = list_tree_at(&root.join("docs"), &ignore, 1, |_| {}).unwrap();
+        assert_eq!(names(&deep), vec!["deep", "a.md"]);
+        assert_eq!(deep[0].note_count, 1);
+    }
+}
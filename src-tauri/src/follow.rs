@@ -0,0 +1,151 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+/// Last bytes read that are compared again on every change, to tell an
+/// append from a rewrite without reading the whole file.
+const TAIL_BYTES: usize = 256;
+
+#[derive(Debug, PartialEq)]
+pub enum FileChange {
+    Unchanged,
+    /// Text added at the end since the last change.
+    Appended(String),
+    /// The file changed some other way; this is all of it.
+    Rewritten(String),
+}
+
+/// A file that is only appended to, like a log or a report being written,
+/// read from where the previous read stopped.
+#[derive(Debug)]
+pub struct FollowedFile {
+    path: PathBuf,
+    /// Bytes read so far.
+    len: u64,
+    /// The last of them, at most `TAIL_BYTES`.
+    tail: Vec<u8>,
+    /// Start of a character cut off by the last read, sent with the next.
+    partial: Vec<u8>,
+}
+
+impl FollowedFile {
+    /// Follows `path` from the end of `content`, as last read from it.
+    pub fn new(path: PathBuf, content: &str) -> Self {
+        let mut file = FollowedFile {
+            path,
+            len: 0,
+            tail: Vec::new(),
+            partial: Vec::new(),
+        };
+        file.reset(content);
+        file
+    }
+
+    fn reset(&mut self, content: &str) {
+        let bytes = content.as_bytes();
+        self.len = bytes.len() as u64;
+        self.tail = bytes[bytes.len().saturating_sub(TAIL_BYTES)..].to_vec();
+        self.partial.clear();
+    }
+
+    fn rewritten(&mut self) -> io::Result<FileChange> {
+        let content = fs::read_to_string(&self.path)?;
+        self.reset(&content);
+        Ok(FileChange::Rewritten(content))
+    }
+
+    /// What changed since the last call, reading only the new bytes unless
+    /// the file was truncated or its end no longer matches.
+    pub fn poll(&mut self) -> io::Result<FileChange> {
+        let mut file = File::open(&self.path)?;
+        if file.metadata()?.len() < self.len {
+            return self.rewritten();
+        }
+
+        file.seek(SeekFrom::Start(self.len - self.tail.len() as u64))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        if !bytes.starts_with(&self.tail) {
+            return self.rewritten();
+        }
+        let added = &bytes[self.tail.len()..];
+        if added.is_empty() {
+            return Ok(FileChange::Unchanged);
+        }
+
+        self.len += added.len() as u64;
+        self.tail.extend_from_slice(added);
+        self.tail
+            .drain(..self.tail.len().saturating_sub(TAIL_BYTES));
+
+        let mut text = std::mem::take(&mut self.partial);
+        text.extend_from_slice(added);
+        // Keep a character still being written for the next read
+        if let Err(e) = std::str::from_utf8(&text) {
+            if e.error_len().is_none() {
+                self.partial = text.split_off(e.valid_up_to());
+            }
+        }
+        if text.is_empty() {
+            return Ok(FileChange::Unchanged);
+        }
+        Ok(FileChange::Appended(
+            String::from_utf8_lossy(&text).into_owned(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+    use std::io::Write;
+
+    #[test]
+    fn test_followed_file() {
+        let scratch = scratch_dir();
+        let path = scratch.path().join("build.md");
+        fs::write(&path, "# Build\n").unwrap();
+        let mut followed = FollowedFile::new(path.clone(), "# Build\n");
+        assert_eq!(followed.poll().unwrap(), FileChange::Unchanged);
+
+        let append = |bytes: &[u8]| {
+            let mut file = File::options().append(true).open(&path).unwrap();
+            file.write_all(bytes).unwrap();
+        };
+        append(b"- step one\n");
+        assert_eq!(
+            followed.poll().unwrap(),
+            FileChange::Appended("- step one\n".to_string())
+        );
+
+        // A character split across two writes arrives whole
+        let check = "✓".as_bytes();
+        append(&[b"- done ", &check[..1]].concat());
+        assert_eq!(
+            followed.poll().unwrap(),
+            FileChange::Appended("- done ".to_string())
+        );
+        append(&check[1..]);
+        assert_eq!(
+            followed.poll().unwrap(),
+            FileChange::Appended("✓".to_string())
+        );
+
+        fs::write(&path, "# Build\n- step 1\n- done ✓").unwrap();
+        assert_eq!(
+            followed.poll().unwrap(),
+            FileChange::Rewritten("# Build\n- step 1\n- done ✓".to_string())
+        );
+        fs::write(&path, "# New\n").unwrap();
+        assert_eq!(
+            followed.poll().unwrap(),
+            FileChange::Rewritten("# New\n".to_string())
+        );
+        append(b"more\n");
+        assert_eq!(
+            followed.poll().unwrap(),
+            FileChange::Appended("more\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,658 @@
+use crate::helper::normalize_path;
+use crate::markdown_parse::{top_level_blocks, BlockSpan};
+use gix::bstr::ByteSlice;
+use gix::ObjectId;
+use serde::Serialize;
+use similar::{DiffOp, TextDiff};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The open document's place inside a git repository.
+#[derive(Serialize, Debug, Clone)]
+pub struct GitRepoInfo {
+    pub root: String,
+    /// Path of the document relative to `root`, with `/` separators.
+    pub relative_path: String,
+    pub branch: Option<String>,
+    pub head: Option<CommitInfo>,
+    /// Whether the document exists in `HEAD`.
+    pub tracked: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CommitInfo {
+    pub id: String,
+    pub short_id: String,
+    pub summary: String,
+    pub author: String,
+    pub email: String,
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+}
+
+/// 1-based line ranges of one change; a pure deletion has `new_lines == 0`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GitDiff {
+    /// The revision compared against, as requested (`HEAD` by default).
+    pub base: String,
+    pub base_commit: Option<CommitInfo>,
+    pub unified: String,
+    pub hunks: Vec<DiffHunk>,
+    /// Blocks of the working copy touched by any hunk, for highlighting.
+    pub changed_blocks: Vec<BlockSpan>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BlockBlame {
+    #[serde(flatten)]
+    pub block: BlockSpan,
+    /// Newest commit that touched any line of the block.
+    pub commit: Option<CommitInfo>,
+    /// The block contains lines that are not committed yet.
+    pub uncommitted: bool,
+}
+
+/// An open repository plus the document's location inside it.
+pub(crate) struct RepoFile {
+    pub repo: gix::Repository,
+    pub root: PathBuf,
+    pub relative_path: String,
+}
+
+impl RepoFile {
+    /// Finds the repository containing `path`, or `None` if there is none.
+    pub fn discover(path: &Path) -> Option<Self> {
+        let absolute = std::fs::canonicalize(path).ok().or_else(|| {
+            // The file may not exist in the working copy (e.g. deleted)
+            let parent = std::fs::canonicalize(path.parent()?).ok()?;
+            Some(parent.join(path.file_name()?))
+        })?;
+        let repo = gix::discover(absolute.parent()?).ok()?;
+        let root = std::fs::canonicalize(repo.workdir()?).ok()?;
+        let relative = absolute.strip_prefix(&root).ok()?;
+        let relative_path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Some(Self {
+            repo,
+            root,
+            relative_path,
+        })
+    }
+
+    pub fn resolve_commit(&self, rev: &str) -> Result<ObjectId, String> {
+        let id = self
+            .repo
+            .rev_parse_single(rev)
+            .map_err(|e| format!("Unknown revision '{}': {}", rev, e))?;
+        let commit = id
+            .object()
+            .map_err(|e| e.to_string())?
+            .peel_to_commit()
+            .map_err(|e| format!("'{}' is not a commit: {}", rev, e))?;
+        Ok(commit.id)
+    }
+
+    /// Content of `relative_path` (relative to the repo root) at `commit`,
+    /// or `None` if the file does not exist there.
+    pub fn blob_at(
+        &self,
+        commit: ObjectId,
+        relative_path: &str,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let commit = self.repo.find_commit(commit).map_err(|e| e.to_string())?;
+        let tree = commit.tree().map_err(|e| e.to_string())?;
+        let entry = tree
+            .lookup_entry_by_path(relative_path)
+            .map_err(|e| e.to_string())?;
+
+        match entry {
+            Some(entry) if entry.mode().is_blob() => {
+                let object = entry.object().map_err(|e| e.to_string())?;
+                Ok(Some(object.data.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn commit_info(&self, id: ObjectId) -> Result<CommitInfo, String> {
+        let commit = self.repo.find_commit(id).map_err(|e| e.to_string())?;
+        let author = commit.author().map_err(|e| e.to_string())?;
+        let summary = commit
+            .message_raw_sloppy()
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_str_lossy()
+            .into_owned();
+        let time = commit.time().map(|t| t.seconds).unwrap_or_default();
+
+        Ok(CommitInfo {
+            id: id.to_string(),
+            short_id: id.to_hex_with_len(7).to_string(),
+            summary,
+            author: author.name.to_str_lossy().into_owned(),
+            email: author.email.to_str_lossy().into_owned(),
+            time,
+        })
+    }
+
+    fn head_commit_id(&self) -> Option<ObjectId> {
+        self.repo.head_id().ok().map(|id| id.detach())
+    }
+}
+
+/// A document pinned to a git revision, written `<rev>:<absolute path>`,
+/// e.g. `HEAD~3:/home/me/project/docs/design.md`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevisionPath {
+    pub rev: String,
+    pub path: PathBuf,
+}
+
+impl RevisionPath {
+    /// Recognizes the normalized `<rev>:<absolute path>` form. A path that
+    /// exists on disk as-is is never treated as a revision path.
+    pub fn parse(spec: &str) -> Option<Self> {
+        if Path::new(spec).exists() {
+            return None;
+        }
+        let (rev, path) = spec.split_once(':')?;
+        if rev.is_empty() || !Path::new(path).is_absolute() {
+            return None;
+        }
+        Some(Self {
+            rev: rev.to_string(),
+            path: PathBuf::from(path),
+        })
+    }
+
+    /// Parses what a user typed on the command line. Like git, a relative
+    /// path after `<rev>:` is relative to the repository root, unless it
+    /// starts with `./` or `../`, in which case it is relative to `cwd`.
+    pub fn from_cli(spec: &str, cwd: &Path) -> Option<Self> {
+        if cwd.join(spec).exists() {
+            return None;
+        }
+        let (rev, path) = spec.split_once(':')?;
+        if rev.is_empty() || path.is_empty() {
+            return None;
+        }
+        let path = if path.starts_with("./") || path.starts_with("../") {
+            cwd.join(path)
+        } else if Path::new(path).is_absolute() {
+            PathBuf::from(path)
+        } else {
+            let repo = gix::discover(cwd).ok()?;
+            repo.workdir()?.join(path)
+        };
+        Some(Self::with_rev(rev, &path, cwd))
+    }
+
+    /// Pins `path` to `rev`. Used for `--rev`, where the path is like any
+    /// other on the command line: absolute, or relative to `cwd`.
+    pub fn with_rev(rev: &str, path: &Path, cwd: &Path) -> Self {
+        Self {
+            rev: rev.to_string(),
+            path: normalize_path(&cwd.join(path)),
+        }
+    }
+
+    pub fn display(&self) -> String {
+        format!("{}:{}", self.rev, self.path.display())
+    }
+
+    fn repo_file(&self) -> Result<RepoFile, String> {
+        RepoFile::discover(&self.path)
+            .ok_or_else(|| format!("'{}' is not inside a git repository", self.path.display()))
+    }
+
+    /// Content of the document at the pinned revision.
+    pub fn read(&self) -> Result<String, String> {
+        let repo_file = self.repo_file()?;
+        let commit = repo_file.resolve_commit(&self.rev)?;
+        let bytes = repo_file
+            .blob_at(commit, &repo_file.relative_path)?
+            .ok_or_else(|| {
+                format!(
+                    "'{}' does not exist at {}",
+                    repo_file.relative_path, self.rev
+                )
+            })?;
+        String::from_utf8(bytes).map_err(|_| "File at this revision is not valid UTF-8".to_string())
+    }
+
+    /// Content of an asset referenced from the document, looked up relative to
+    /// the document inside the same revision.
+    pub fn read_asset(&self, asset_name: &str) -> Option<Vec<u8>> {
+        let repo_file = self.repo_file().ok()?;
+        let commit = repo_file.resolve_commit(&self.rev).ok()?;
+
+        let dir = repo_file
+            .relative_path
+            .rsplit_once('/')
+            .map(|(dir, _)| dir)
+            .unwrap_or("");
+        let decoded = urlencoding::decode(asset_name).ok()?;
+        let asset_path = normalize_path(&Path::new(dir).join(decoded.as_ref()));
+        let asset_path = asset_path.to_str()?.trim_start_matches('/');
+
+        repo_file.blob_at(commit, asset_path).ok().flatten()
+    }
+}
+
+pub fn repo_info(path: &Path) -> Option<GitRepoInfo> {
+    let repo_file = RepoFile::discover(path)?;
+    let head_id = repo_file.head_commit_id();
+
+    let branch = repo_file
+        .repo
+        .head_name()
+        .ok()
+        .flatten()
+        .map(|name| name.shorten().to_str_lossy().into_owned());
+    let head = head_id.and_then(|id| repo_file.commit_info(id).ok());
+    let tracked = head_id
+        .and_then(|id| {
+            repo_file
+                .blob_at(id, &repo_file.relative_path)
+                .ok()
+                .flatten()
+        })
+        .is_some();
+
+    Some(GitRepoInfo {
+        root: repo_file.root.to_string_lossy().into_owned(),
+        relative_path: repo_file.relative_path.clone(),
+        branch,
+        head,
+        tracked,
+    })
+}
+
+pub fn diff_against(path: &Path, rev: Option<&str>) -> Result<GitDiff, String> {
+    let repo_file = RepoFile::discover(path).ok_or("Not inside a git repository")?;
+    let base = rev.unwrap_or("HEAD").to_string();
+    let commit = repo_file.resolve_commit(&base)?;
+
+    let old = repo_file
+        .blob_at(commit, &repo_file.relative_path)?
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+    let current = std::fs::read_to_string(path).unwrap_or_default();
+
+    let diff = TextDiff::from_lines(&old, &current);
+    let hunks = hunks_of(&diff);
+    let unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(
+            &format!("{}:{}", base, repo_file.relative_path),
+            &repo_file.relative_path,
+        )
+        .to_string();
+
+    Ok(GitDiff {
+        base_commit: repo_file.commit_info(commit).ok(),
+        base,
+        unified,
+        changed_blocks: changed_blocks(&top_level_blocks(&current), &hunks),
+        hunks,
+    })
+}
+
+/// Blocks of `new` that differ from `old`, as `changed_blocks` marks them.
+pub fn changed_blocks_since(old: &str, new: &str) -> Vec<BlockSpan> {
+    let hunks = hunks_of(&TextDiff::from_lines(old, new));
+    changed_blocks(&top_level_blocks(new), &hunks)
+}
+
+fn hunks_of<'a>(diff: &TextDiff<'a, 'a, 'a, str>) -> Vec<DiffHunk> {
+    diff.ops()
+        .iter()
+        .filter(|op| !matches!(op, DiffOp::Equal { .. }))
+        .map(|op| {
+            let (old, new) = (op.old_range(), op.new_range());
+            DiffHunk {
+                old_start: old.start + 1,
+                old_lines: old.len(),
+                new_start: new.start + 1,
+                new_lines: new.len(),
+            }
+        })
+        .collect()
+}
+
+/// Blocks overlapping a hunk. A pure deletion marks the block right after the
+/// removed lines (or the last block when the end of the file was removed).
+fn changed_blocks(blocks: &[BlockSpan], hunks: &[DiffHunk]) -> Vec<BlockSpan> {
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(i, block)| {
+            hunks.iter().any(|hunk| {
+                if hunk.new_lines == 0 {
+                    let next = blocks
+                        .iter()
+                        .position(|b| b.end_line >= hunk.new_start)
+                        .unwrap_or(blocks.len().saturating_sub(1));
+                    return next == *i;
+                }
+                let end = hunk.new_start + hunk.new_lines - 1;
+                hunk.new_start <= block.end_line && end >= block.start_line
+            })
+        })
+        .map(|(_, block)| *block)
+        .collect()
+}
+
+pub fn blame_blocks(path: &Path) -> Result<Vec<BlockBlame>, String> {
+    let repo_file = RepoFile::discover(path).ok_or("Not inside a git repository")?;
+    let current = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let blocks = top_level_blocks(&current);
+
+    let head = repo_file.head_commit_id();
+    let committed = match head {
+        Some(id) => repo_file.blob_at(id, &repo_file.relative_path)?,
+        None => None,
+    };
+
+    let (Some(head), Some(committed)) = (head, committed) else {
+        // Untracked or no commits yet: everything is new
+        return Ok(blocks
+            .into_iter()
+            .map(|block| BlockBlame {
+                block,
+                commit: None,
+                uncommitted: true,
+            })
+            .collect());
+    };
+
+    let outcome = repo_file
+        .repo
+        .blame_file(
+            repo_file.relative_path.as_bytes().as_bstr(),
+            head,
+            gix::blame::Options {
+                diff_algorithm: gix::diff::blob::Algorithm::Histogram,
+                range: Default::default(),
+                since: None,
+                rewrites: Some(Default::default()),
+                debug_track_path: false,
+            },
+        )
+        .map_err(|e| format!("Blame failed: {}", e))?;
+
+    // Commit for every line of the committed file (0-based)
+    let committed = String::from_utf8_lossy(&committed).into_owned();
+    let mut line_commits: Vec<Option<ObjectId>> = vec![None; committed.lines().count()];
+    for entry in &outcome.entries {
+        let start = entry.start_in_blamed_file as usize;
+        let end = (start + entry.len.get() as usize).min(line_commits.len());
+        for slot in &mut line_commits[start.min(end)..end] {
+            *slot = Some(entry.commit_id);
+        }
+    }
+
+    let working_to_committed = map_lines(&committed, &current);
+
+    let mut infos: HashMap<ObjectId, Option<CommitInfo>> = HashMap::new();
+    let mut result = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        let mut uncommitted = false;
+        let mut newest: Option<CommitInfo> = None;
+
+        for line in block.start_line..=block.end_line {
+            let commit = working_to_committed
+                .get(line - 1)
+                .copied()
+                .flatten()
+                .and_then(|old| line_commits.get(old).copied().flatten());
+            let Some(commit) = commit else {
+                uncommitted = true;
+                continue;
+            };
+
+            let info = infos
+                .entry(commit)
+                .or_insert_with(|| repo_file.commit_info(commit).ok())
+                .clone();
+            if let Some(info) = info {
+                if newest.as_ref().map(|n| info.time > n.time).unwrap_or(true) {
+                    newest = Some(info);
+                }
+            }
+        }
+
+        result.push(BlockBlame {
+            block,
+            commit: newest,
+            uncommitted,
+        });
+    }
+
+    Ok(result)
+}
+
+/// For every line of `new` (0-based), the line of `old` it was carried over
+/// from, or `None` if it was added or changed.
+fn map_lines(old: &str, new: &str) -> Vec<Option<usize>> {
+    let diff = TextDiff::from_lines(old, new);
+    let mut mapping = vec![None; new.lines().count()];
+
+    for op in diff.ops() {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = *op
+        {
+            for offset in 0..len {
+                if let Some(slot) = mapping.get_mut(new_index + offset) {
+                    *slot = Some(old_index + offset);
+                }
+            }
+        }
+    }
+
+    mapping
+}
+
+#[tauri::command]
+pub async fn git_repo_info(path: String) -> Option<GitRepoInfo> {
+    tauri::async_runtime::spawn_blocking(move || repo_info(Path::new(&path)))
+        .await
+        .ok()
+        .flatten()
+}
+
+#[tauri::command]
+pub async fn git_diff(path: String, rev: Option<String>) -> Result<GitDiff, String> {
+    tauri::async_runtime::spawn_blocking(move || diff_against(Path::new(&path), rev.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn git_blame_blocks(path: String) -> Result<Vec<BlockBlame>, String> {
+    tauri::async_runtime::spawn_blocking(move || blame_blocks(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+
+    fn span(start_line: usize, end_line: usize) -> BlockSpan {
+        BlockSpan {
+            start_line,
+            end_line,
+        }
+    }
+
+    #[test]
+    fn test_map_lines() {
+        let old = "a\nb\nc\n";
+        let new = "a\nx\nb\nc\n";
+        assert_eq!(map_lines(old, new), vec![Some(0), None, Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn test_hunks_and_changed_blocks() {
+        let old = "# Title\n\nfirst\n\nsecond\n\nthird\n";
+        let new = "# Title\n\nfirst changed\n\nsecond\n";
+        let diff = TextDiff::from_lines(old, new);
+        let hunks = hunks_of(&diff);
+
+        assert_eq!(
+            hunks,
+            vec![
+                DiffHunk {
+                    old_start: 3,
+                    old_lines: 1,
+                    new_start: 3,
+                    new_lines: 1
+                },
+                DiffHunk {
+                    old_start: 6,
+                    old_lines: 2,
+                    new_start: 6,
+                    new_lines: 0
+                },
+            ]
+        );
+
+        let blocks = top_level_blocks(new);
+        assert_eq!(blocks, vec![span(1, 1), span(3, 3), span(5, 5)]);
+        // The edited paragraph, plus the last block for the removal at the end
+        assert_eq!(
+            changed_blocks(&blocks, &hunks),
+            vec![span(3, 3), span(5, 5)]
+        );
+    }
+
+    #[test]
+    fn test_changed_blocks_since() {
+        let old = "# Title\n\nfirst\n\nsecond\n";
+        let new = "# Title\n\nintro\n\nfirst\n\nsecond, edited\n";
+        assert_eq!(changed_blocks_since(old, new), vec![span(3, 3), span(7, 7)]);
+        assert!(changed_blocks_since(new, new).is_empty());
+    }
+
+    #[test]
+    fn test_revision_path_parse() {
+        let parsed = RevisionPath::parse("HEAD~3:/nonexistent/docs/design.md").unwrap();
+        assert_eq!(parsed.rev, "HEAD~3");
+        assert_eq!(parsed.path, PathBuf::from("/nonexistent/docs/design.md"));
+        assert_eq!(parsed.display(), "HEAD~3:/nonexistent/docs/design.md");
+
+        assert!(RevisionPath::parse("/nonexistent/plain.md").is_none());
+        assert!(RevisionPath::parse("HEAD:relative.md").is_none());
+        assert!(RevisionPath::parse(":/nonexistent/a.md").is_none());
+    }
+
+    #[test]
+    fn test_revision_path_from_cli_relative_to_cwd() {
+        let cwd = Path::new("/nonexistent/project/docs");
+        let parsed = RevisionPath::from_cli("v1.0:../README.md", cwd).unwrap();
+        assert_eq!(parsed.rev, "v1.0");
+        assert_eq!(parsed.path, PathBuf::from("/nonexistent/project/README.md"));
+    }
+
+    #[test]
+    fn test_revision_path_with_rev_relative_to_cwd() {
+        let cwd = Path::new("/nonexistent/project");
+        let pinned = RevisionPath::with_rev("v1.0", Path::new("notes/plan.md"), cwd);
+        assert_eq!(
+            pinned.path,
+            PathBuf::from("/nonexistent/project/notes/plan.md")
+        );
+        let pinned = RevisionPath::with_rev("v1.0", Path::new("./notes/../plan.md"), cwd);
+        assert_eq!(pinned.path, PathBuf::from("/nonexistent/project/plan.md"));
+    }
+
+    /// Runs `git` in `dir` with a fixed identity, to build test repositories.
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(["-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .expect("Failed to run git");
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_diff_and_blame_in_a_repository() {
+        let scratch = scratch_dir();
+        let root = scratch.path();
+        let note = root.join("note.md");
+        git(root, &["init", "-q"]);
+        std::fs::write(&note, "# Title\n\nfirst\n\nsecond\n").unwrap();
+        git(root, &["add", "note.md"]);
+        git(root, &["commit", "-q", "-m", "First"]);
+        std::fs::write(&note, "# Title\n\nfirst\n\nsecond, edited\n\nthird\n").unwrap();
+        git(root, &["commit", "-q", "-am", "Second"]);
+        std::fs::write(
+            &note,
+            "# Title\n\nfirst, uncommitted\n\nsecond, edited\n\nthird\n",
+        )
+        .unwrap();
+
+        let diff = diff_against(&note, None).unwrap();
+        assert_eq!(diff.base_commit.unwrap().summary, "Second");
+        assert_eq!(diff.changed_blocks, vec![span(3, 3)]);
+        assert!(diff.unified.contains("-first\n+first, uncommitted\n"));
+
+        let diff = diff_against(&note, Some("HEAD~1")).unwrap();
+        assert_eq!(diff.base_commit.unwrap().summary, "First");
+        assert_eq!(
+            diff.changed_blocks,
+            vec![span(3, 3), span(5, 5), span(7, 7)]
+        );
+
+        let blame: Vec<(BlockSpan, Option<String>, bool)> = blame_blocks(&note)
+            .unwrap()
+            .into_iter()
+            .map(|b| (b.block, b.commit.map(|c| c.summary), b.uncommitted))
+            .collect();
+        assert_eq!(
+            blame,
+            vec![
+                (span(1, 1), Some("First".to_string()), false),
+                (span(3, 3), None, true),
+                (span(5, 5), Some("Second".to_string()), false),
+                (span(7, 7), Some("Second".to_string()), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_a_repository() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let note = dir.join("note.md");
+        std::fs::write(&note, "# hi\n").unwrap();
+
+        // The temp dir might itself live inside a repository on some machines
+        if RepoFile::discover(&note).is_none() {
+            assert!(repo_info(&note).is_none());
+            assert!(diff_against(&note, None).is_err());
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::config::Config;
+use crate::config::{
+    config_schema, is_valid_css_length, parse_config, upgrade_config_source, Config,
+    ConfigReport, CONFIG_SCHEMA_FILE_NAME, CONFIG_VERSION,
+};
 use parking_lot::RwLock;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -22,32 +25,157 @@ pub fn get_path(app_handle: &tauri::AppHandle, file: &str) -> PathBuf {
     path
 }
 
-pub fn get_config(app_handle: &tauri::AppHandle) -> Result<Config, Box<dyn std::error::Error>> {
-    // Check cache first
-    {
-        let cache = CONFIG_CACHE.read();
-        if let Some(ref config) = *cache {
-            return Ok(config.clone());
+/// Resolves `.` and `..` without touching the filesystem, since the path may
+/// not exist (yet, or in the working copy).
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
         }
     }
-    
-    // Load from disk
-    let config_path = get_path(app_handle, "config.json");
+    normalized
+}
 
-    if !config_path.exists() {
-        return Err("Config file not found".into());
+/// Path of `to` relative to the directory `from_dir`, e.g. `../assets/a.png`.
+pub fn relative_path(from_dir: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from_dir.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
     }
+    relative
+}
 
-    let config_data = fs::read_to_string(&config_path)?;
-    let config: Config = serde_json::from_str(&config_data)?;
-    
-    // Update cache
-    {
-        let mut cache = CONFIG_CACHE.write();
-        *cache = Some(config.clone());
+/// Like `get_path`, but under the app data directory, for state that is
+/// neither configuration nor disposable cache.
+pub fn get_data_path(app_handle: &tauri::AppHandle, name: &str) -> PathBuf {
+    let mut path = app_handle
+        .path()
+        .app_data_dir()
+        .expect("Error: Critical failure retrieving app data directory");
+
+    if !path.exists() {
+        let _ = fs::create_dir_all(&path);
     }
 
-    Ok(config)
+    path.push(name);
+    path
+}
+
+/// Where the version `from_version` file is backed up before an upgrade:
+/// `config.v<N>.bak.json`, or `config.v<N>.<n>.bak.json` next to earlier ones.
+fn config_backup_path(path: &Path, from_version: u32) -> PathBuf {
+    let mut backup = path.with_file_name(format!("config.v{}.bak.json", from_version));
+    let mut n = 1;
+    while backup.exists() {
+        backup = path.with_file_name(format!("config.v{}.{}.bak.json", from_version, n));
+        n += 1;
+    }
+    backup
+}
+
+/// Reads `config.json`, upgrading it to the current layout first (the old
+/// file is kept as `config.v<N>.bak.json`), and logs any validation issues.
+fn read_config_file(path: &Path) -> Result<ConfigReport, String> {
+    let mut content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    if let Some((from_version, upgraded)) = upgrade_config_source(&content) {
+        let backup = config_backup_path(path, from_version);
+        fs::write(&backup, &content).map_err(|e| format!("Failed to back up config: {}", e))?;
+        fs::write(path, &upgraded).map_err(|e| e.to_string())?;
+        println!(
+            "Migrated config.json from version {} to {} (backup: {})",
+            from_version,
+            CONFIG_VERSION,
+            backup.display()
+        );
+        content = upgraded;
+    }
+
+    let report = parse_config(&content);
+    for issue in &report.issues {
+        eprintln!("config.json: {}", issue);
+    }
+    Ok(report)
+}
+
+/// Re-reads `config.json` from disk, ignoring the cache, and stores the result
+/// in the cache. Used when the file is edited outside the app.
+pub fn reload_config(app_handle: &tauri::AppHandle) -> Result<ConfigReport, String> {
+    reload_config_into(&get_path(app_handle, "config.json"), &CONFIG_CACHE)
+}
+
+/// `reload_config` for the file at `path` and `cache`. A file that is not
+/// valid JSON, as while it is being edited, keeps the cached config in
+/// effect instead of resetting every setting to its default, and is never
+/// cached itself.
+fn reload_config_into(path: &Path, cache: &RwLock<Option<Config>>) -> Result<ConfigReport, String> {
+    let mut report = read_config_file(path)?;
+
+    let mut cache = cache.write();
+    if !report.unusable {
+        *cache = Some(report.config.clone());
+    } else if let Some(last_good) = cache.as_ref() {
+        report.config = last_good.clone();
+    }
+
+    Ok(report)
+}
+
+/// Validates `content`, or the on-disk `config.json` when `content` is `None`.
+pub fn validate_config_source(
+    app_handle: &tauri::AppHandle,
+    content: Option<String>,
+) -> Result<ConfigReport, String> {
+    let content = match content {
+        Some(content) => content,
+        None => {
+            let path = get_path(app_handle, "config.json");
+            if !path.exists() {
+                return Ok(parse_config("{}"));
+            }
+            fs::read_to_string(&path).map_err(|e| e.to_string())?
+        }
+    };
+
+    Ok(parse_config(&content))
+}
+
+/// Writes `config.schema.json` next to `config.json` so editors can offer
+/// completion and validation via `"$schema": "./config.schema.json"`.
+pub fn write_config_schema(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let path = get_path(app_handle, CONFIG_SCHEMA_FILE_NAME);
+    let schema = serde_json::to_string_pretty(&config_schema()).map_err(|e| e.to_string())?;
+
+    if fs::read_to_string(&path).ok().as_deref() == Some(schema.as_str()) {
+        return Ok(());
+    }
+
+    fs::write(path, schema).map_err(|e| e.to_string())
+}
+
+/// Empty directory for a test, removed with everything in it when dropped.
+#[cfg(test)]
+pub fn scratch_dir() -> tempfile::TempDir {
+    tempfile::Builder::new()
+        .prefix("readtext-")
+        .tempdir()
+        .expect("Failed to create a temp dir")
+}
+
+pub fn cached_config() -> Option<Config> {
+    CONFIG_CACHE.read().as_ref().cloned()
 }
 
 pub fn get_config_path(app_handle: &tauri::AppHandle) -> PathBuf {
@@ -70,17 +198,21 @@ pub fn load_config_with_override(app_handle: &tauri::AppHandle, target_path: Opt
     } else {
         let path = get_path(app_handle, "config.json");
         if !path.exists() {
-            let default_config = Config::default();
+            let default_config = Config {
+                schema: Some(format!("./{}", CONFIG_SCHEMA_FILE_NAME)),
+                ..Config::default()
+            };
             save_config(app_handle, &default_config)?;
             default_config
         } else {
-            let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-            let cfg: Config = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-            
-            // Update cache
-            let mut cache = CONFIG_CACHE.write();
-            *cache = Some(cfg.clone());
-            cfg
+            // A file that does not parse is read again next time, and its
+            // defaults are never cached or saved over it
+            let report = read_config_file(&path)?;
+            if !report.unusable {
+                let mut cache = CONFIG_CACHE.write();
+                *cache = Some(report.config.clone());
+            }
+            report.config
         }
     };
 
@@ -101,7 +233,11 @@ pub fn load_config_with_override(app_handle: &tauri::AppHandle, target_path: Opt
                         // Manually merge known fields for now or use a generic merge
                         // For --max-width, it's specific
                         if let Some(max_width) = override_val.get("max_width").and_then(|v| v.as_str()) {
-                            config.max_width = max_width.to_string();
+                            if is_valid_css_length(max_width) {
+                                config.layout.max_width = max_width.to_string();
+                            } else {
+                                eprintln!(".readtext.json: ignoring invalid max_width '{}'", max_width);
+                            }
                         }
                         
                         // Add more overrides here if needed
@@ -116,6 +252,23 @@ pub fn load_config_with_override(app_handle: &tauri::AppHandle, target_path: Opt
 
 pub fn save_config(app_handle: &tauri::AppHandle, config: &Config) -> Result<(), String> {
     let path = get_path(app_handle, "config.json");
+
+    // Settings saved over a file that does not parse would replace the
+    // user's edits with defaults
+    if let Ok(content) = fs::read_to_string(&path) {
+        let report = parse_config(&content);
+        if report.unusable {
+            let reason = report.issues.first().map(ToString::to_string).unwrap_or_default();
+            return Err(format!("config.json could not be read ({}); fix it first", reason));
+        }
+        // Settings this build does not know would be dropped
+        if report.config.version > CONFIG_VERSION {
+            return Err(format!(
+                "config.json was written by a newer readtext (version {}); not overwriting it",
+                report.config.version
+            ));
+        }
+    }
     
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
@@ -132,3 +285,65 @@ pub fn save_config(app_handle: &tauri::AppHandle, config: &Config) -> Result<(),
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_keeps_config_after_syntax_error() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let path = dir.join("config.json");
+        let cache = RwLock::new(None);
+
+        fs::write(&path, r#"{ "version": 1, "layout": { "max_width": "900px" } }"#).unwrap();
+        let report = reload_config_into(&path, &cache).unwrap();
+        assert_eq!(report.config.layout.max_width, "900px");
+
+        // A typo while editing keeps the settings in effect
+        fs::write(&path, r#"{ "version": 1, "layout": { "max_width": "700px" }, }"#).unwrap();
+        let report = reload_config_into(&path, &cache).unwrap();
+        assert!(report.unusable);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.config.layout.max_width, "900px");
+        assert_eq!(cache.read().as_ref().unwrap().layout.max_width, "900px");
+
+        fs::write(&path, r#"{ "version": 1, "layout": { "max_width": "700px" } }"#).unwrap();
+        let report = reload_config_into(&path, &cache).unwrap();
+        assert_eq!(report.config.layout.max_width, "700px");
+        assert_eq!(cache.read().as_ref().unwrap().layout.max_width, "700px");
+
+    }
+
+    #[test]
+    fn test_upgrade_keeps_every_backup() {
+        let scratch = scratch_dir();
+        let path = scratch.path().join("config.json");
+        for width in ["900px", "700px"] {
+            fs::write(&path, format!(r#"{{ "max_width": "{}" }}"#, width)).unwrap();
+            let report = read_config_file(&path).unwrap();
+            assert_eq!(report.config.layout.max_width, width);
+        }
+
+        let backup = |name: &str| fs::read_to_string(scratch.path().join(name)).unwrap();
+        assert!(backup("config.v0.bak.json").contains("900px"));
+        assert!(backup("config.v0.1.bak.json").contains("700px"));
+    }
+
+    #[test]
+    fn test_unusable_config_is_not_cached() {
+        let scratch = scratch_dir();
+        let path = scratch.path().join("config.json");
+        let cache = RwLock::new(None);
+
+        fs::write(&path, r#"{ "version": 1, "layout": { "max_width": "700px" }, }"#).unwrap();
+        let report = reload_config_into(&path, &cache).unwrap();
+        assert!(report.unusable);
+        assert!(cache.read().is_none());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            r#"{ "version": 1, "layout": { "max_width": "700px" }, }"#
+        );
+    }
+}
@@ -0,0 +1,430 @@
+use crate::file_io::{atomic_write, content_hash};
+use crate::helper::get_data_path;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Snapshots kept per file; the oldest are dropped first.
+const MAX_VERSIONS_PER_FILE: usize = 100;
+/// Snapshots older than this are dropped, except the newest one.
+const MAX_VERSION_AGE_MS: u64 = 90 * 24 * 60 * 60 * 1000;
+
+/// Held while history is written, as saves run on several threads: two saves
+/// of one file must not both extend the same index, and garbage collection
+/// must not see an object before the index that refers to it.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Local snapshot history of edited notes, stored under the app data dir as
+/// gzip-compressed, content-addressed blobs plus one small index per file.
+///
+/// ```text
+/// history/objects/<sha256>.gz
+/// history/files/<sha256 of path, 16 chars>.json
+/// ```
+pub struct History {
+    root: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VersionInfo {
+    pub id: String,
+    pub hash: String,
+    pub saved_ms: u64,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct FileIndex {
+    path: String,
+    /// Oldest first.
+    versions: Vec<VersionInfo>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VersionDiff {
+    pub version: VersionInfo,
+    /// Unified diff from the version to the current file.
+    pub unified: String,
+    pub added: usize,
+    pub removed: usize,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+impl History {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn for_app(app_handle: &tauri::AppHandle) -> Self {
+        Self::new(get_data_path(app_handle, "history"))
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.root.join("objects").join(format!("{}.gz", hash))
+    }
+
+    fn index_path(&self, file: &Path) -> PathBuf {
+        let key = content_hash(file.to_string_lossy().as_bytes());
+        self.root.join("files").join(format!("{}.json", &key[..16]))
+    }
+
+    fn load_index(&self, file: &Path) -> FileIndex {
+        fs::read_to_string(self.index_path(file))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_else(|| FileIndex {
+                path: file.to_string_lossy().into_owned(),
+                versions: Vec::new(),
+            })
+    }
+
+    fn store_index(&self, file: &Path, index: &FileIndex) -> io::Result<()> {
+        let path = self.index_path(file);
+        fs::create_dir_all(path.parent().expect("index path has a parent"))?;
+        let json = serde_json::to_string_pretty(index).map_err(io::Error::other)?;
+        atomic_write(&path, json.as_bytes())
+    }
+
+    fn write_object(&self, hash: &str, content: &[u8]) -> io::Result<()> {
+        let path = self.object_path(hash);
+        if path.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(path.parent().expect("object path has a parent"))?;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content)?;
+        atomic_write(&path, &encoder.finish()?)
+    }
+
+    /// Content of a version, which fails for a file that is not UTF-8 text.
+    fn read_object(&self, hash: &str) -> io::Result<String> {
+        let compressed = fs::read(self.object_path(hash))?;
+        let mut content = Vec::new();
+        GzDecoder::new(&compressed[..]).read_to_end(&mut content)?;
+        String::from_utf8(content)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not UTF-8 text"))
+    }
+
+    /// Records `content` as the newest version of `file`. Returns `None` when
+    /// it is identical to the newest recorded version.
+    pub fn record(&self, file: &Path, content: &[u8]) -> io::Result<Option<VersionInfo>> {
+        self.record_at(file, content, now_ms())
+    }
+
+    fn record_at(
+        &self,
+        file: &Path,
+        content: &[u8],
+        saved_ms: u64,
+    ) -> io::Result<Option<VersionInfo>> {
+        let _guard = WRITE_LOCK.lock().unwrap();
+        self.record_locked(file, content, saved_ms)
+    }
+
+    /// `record_at` for a caller holding `WRITE_LOCK`.
+    fn record_locked(
+        &self,
+        file: &Path,
+        content: &[u8],
+        saved_ms: u64,
+    ) -> io::Result<Option<VersionInfo>> {
+        let hash = content_hash(content);
+        let mut index = self.load_index(file);
+
+        if index.versions.last().map(|v| v.hash.as_str()) == Some(hash.as_str()) {
+            return Ok(None);
+        }
+
+        self.write_object(&hash, content)?;
+
+        let version = VersionInfo {
+            id: format!("{}-{}", saved_ms, &hash[..12]),
+            hash,
+            saved_ms,
+            size: content.len() as u64,
+        };
+        index.versions.push(version.clone());
+
+        let pruned = prune(&mut index.versions, saved_ms);
+        self.store_index(file, &index)?;
+
+        if pruned {
+            self.collect_garbage()?;
+        }
+
+        Ok(Some(version))
+    }
+
+    /// Newest first.
+    pub fn list(&self, file: &Path) -> Vec<VersionInfo> {
+        let mut versions = self.load_index(file).versions;
+        versions.reverse();
+        versions
+    }
+
+    fn find(&self, file: &Path, version_id: &str) -> Result<VersionInfo, String> {
+        self.load_index(file)
+            .versions
+            .into_iter()
+            .find(|v| v.id == version_id)
+            .ok_or_else(|| format!("Version '{}' not found", version_id))
+    }
+
+    pub fn content(&self, file: &Path, version_id: &str) -> Result<String, String> {
+        let version = self.find(file, version_id)?;
+        self.read_object(&version.hash)
+            .map_err(|e| format!("Failed to read version: {}", e))
+    }
+
+    pub fn diff(&self, file: &Path, version_id: &str) -> Result<VersionDiff, String> {
+        let version = self.find(file, version_id)?;
+        let old = self
+            .read_object(&version.hash)
+            .map_err(|e| format!("Failed to read version: {}", e))?;
+        // Compared against an empty text instead, it would look all removed
+        let current = fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+
+        let diff = TextDiff::from_lines(&old, &current);
+        let (mut added, mut removed) = (0, 0);
+        for change in diff.iter_all_changes() {
+            match change.tag() {
+                similar::ChangeTag::Insert => added += 1,
+                similar::ChangeTag::Delete => removed += 1,
+                similar::ChangeTag::Equal => {}
+            }
+        }
+
+        let unified = diff
+            .unified_diff()
+            .context_radius(3)
+            .header(&version.id, "current")
+            .to_string();
+
+        Ok(VersionDiff {
+            version,
+            unified,
+            added,
+            removed,
+        })
+    }
+
+    /// Writes `version_id` back to `file`, first recording the current content
+    /// so the restore itself can be undone.
+    pub fn restore(&self, file: &Path, version_id: &str) -> Result<String, String> {
+        let content = self.content(file, version_id)?;
+
+        let _guard = WRITE_LOCK.lock().unwrap();
+        if let Ok(current) = fs::read(file) {
+            self.record_locked(file, &current, now_ms())
+                .map_err(|e| e.to_string())?;
+        }
+        atomic_write(file, content.as_bytes()).map_err(|e| format!("Failed to restore: {}", e))?;
+        self.record_locked(file, content.as_bytes(), now_ms())
+            .map_err(|e| e.to_string())?;
+
+        Ok(content)
+    }
+
+    /// Deletes objects no index refers to any more.
+    fn collect_garbage(&self) -> io::Result<()> {
+        let mut referenced = HashSet::new();
+        if let Ok(entries) = fs::read_dir(self.root.join("files")) {
+            for entry in entries.flatten() {
+                let index: Option<FileIndex> = fs::read_to_string(entry.path())
+                    .ok()
+                    .and_then(|content| serde_json::from_str(&content).ok());
+                if let Some(index) = index {
+                    referenced.extend(index.versions.into_iter().map(|v| v.hash));
+                }
+            }
+        }
+
+        if let Ok(entries) = fs::read_dir(self.root.join("objects")) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let hash = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or_default();
+                if !referenced.contains(hash) {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies the retention limits; returns whether anything was dropped.
+fn prune(versions: &mut Vec<VersionInfo>, now_ms: u64) -> bool {
+    let before = versions.len();
+    let newest = versions.len().saturating_sub(1);
+
+    let mut position = 0;
+    versions.retain(|v| {
+        let keep = position == newest || now_ms.saturating_sub(v.saved_ms) <= MAX_VERSION_AGE_MS;
+        position += 1;
+        keep
+    });
+
+    let excess = versions.len().saturating_sub(MAX_VERSIONS_PER_FILE);
+    versions.drain(..excess);
+
+    versions.len() != before
+}
+
+#[tauri::command]
+pub fn list_versions(app_handle: tauri::AppHandle, path: String) -> Vec<VersionInfo> {
+    History::for_app(&app_handle).list(Path::new(&path))
+}
+
+#[tauri::command]
+pub fn diff_version(
+    app_handle: tauri::AppHandle,
+    path: String,
+    version_id: String,
+) -> Result<VersionDiff, String> {
+    History::for_app(&app_handle).diff(Path::new(&path), &version_id)
+}
+
+#[tauri::command]
+pub fn restore_version(
+    app_handle: tauri::AppHandle,
+    path: String,
+    version_id: String,
+) -> Result<String, String> {
+    History::for_app(&app_handle).restore(Path::new(&path), &version_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+
+    fn object_count(history: &History) -> usize {
+        fs::read_dir(history.root.join("objects")).unwrap().count()
+    }
+
+    #[test]
+    fn test_record_deduplicates() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let history = History::new(dir.join("history"));
+        let note = dir.join("note.md");
+
+        assert!(history.record_at(&note, b"a", 1).unwrap().is_some());
+        assert!(history.record_at(&note, b"a", 2).unwrap().is_none());
+        assert!(history.record_at(&note, b"b", 3).unwrap().is_some());
+        assert!(history.record_at(&note, b"a", 4).unwrap().is_some());
+
+        let versions = history.list(&note);
+        assert_eq!(versions.len(), 3);
+        assert_eq!(versions[0].saved_ms, 4);
+        // "a" is stored once even though it appears twice in the history
+        assert_eq!(object_count(&history), 2);
+    }
+
+    #[test]
+    fn test_diff_and_restore() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let history = History::new(dir.join("history"));
+        let note = dir.join("note.md");
+
+        fs::write(&note, "one\ntwo\n").unwrap();
+        let first = history.record(&note, b"one\ntwo\n").unwrap().unwrap();
+        fs::write(&note, "one\nthree\nfour\n").unwrap();
+
+        let diff = history.diff(&note, &first.id).unwrap();
+        assert_eq!((diff.added, diff.removed), (2, 1));
+        assert!(diff.unified.contains("-two"));
+        assert!(diff.unified.contains("+three"));
+
+        let restored = history.restore(&note, &first.id).unwrap();
+        assert_eq!(restored, "one\ntwo\n");
+        assert_eq!(fs::read_to_string(&note).unwrap(), "one\ntwo\n");
+
+        // The overwritten content was kept, so the restore can be undone
+        let versions = history.list(&note);
+        assert_eq!(versions.len(), 3);
+        let undo = history.content(&note, &versions[1].id).unwrap();
+        assert_eq!(undo, "one\nthree\nfour\n");
+
+        assert!(history.diff(&note, "missing").is_err());
+
+        // A file that is no longer text is not diffed as if it were emptied
+        fs::write(&note, [0xff, 0xfe, 0x00]).unwrap();
+        assert!(history.diff(&note, &first.id).is_err());
+    }
+
+    #[test]
+    fn test_concurrent_records_keep_every_version() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let note = dir.join("note.md");
+
+        std::thread::scope(|scope| {
+            for i in 0..8u64 {
+                let (dir, note) = (&dir, &note);
+                scope.spawn(move || {
+                    History::new(dir.join("history"))
+                        .record_at(note, format!("v{}", i).as_bytes(), 10 + i)
+                        .unwrap();
+                });
+            }
+        });
+
+        assert_eq!(History::new(dir.join("history")).list(&note).len(), 8);
+    }
+
+    #[test]
+    fn test_retention_limits() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let history = History::new(dir.join("history"));
+        let note = dir.join("note.md");
+
+        history.record_at(&note, b"ancient", 0).unwrap();
+        let now = MAX_VERSION_AGE_MS + 10;
+        for i in 0..MAX_VERSIONS_PER_FILE + 5 {
+            let content = format!("v{}", i);
+            history
+                .record_at(&note, content.as_bytes(), now + i as u64)
+                .unwrap();
+        }
+
+        let versions = history.list(&note);
+        assert_eq!(versions.len(), MAX_VERSIONS_PER_FILE);
+        assert!(versions.iter().all(|v| v.saved_ms >= now));
+        assert_eq!(object_count(&history), MAX_VERSIONS_PER_FILE);
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_even_if_old() {
+        let mut versions = vec![VersionInfo {
+            id: "0-x".to_string(),
+            hash: "x".to_string(),
+            saved_ms: 0,
+            size: 1,
+        }];
+        assert!(!prune(&mut versions, MAX_VERSION_AGE_MS * 2));
+        assert_eq!(versions.len(), 1);
+    }
+}
@@ -1,6 +1,16 @@
-use crate::helper::get_config;
+use crate::asset_protocol::allow_file;
+use crate::config::Config;
+use crate::git::RevisionPath;
+use crate::helper::{load_config, normalize_path};
+use crate::links::index_directory_links;
+use crate::obsidian::{find_vault, VaultSettings};
+use crate::orphans::QUARANTINE_DIR;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use rusqlite::{params, Connection, Result, Transaction};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Mutex;
 use std::time::SystemTime;
 use std::{path::Path, time::Instant};
@@ -8,8 +18,46 @@ use tauri::{AppHandle, Manager, State};
 use urlencoding::decode;
 use walkdir::WalkDir;
 
-const DATABASE_FILE_NAME: &str = "cache.db";
-const SUPPORTED_IMAGE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "webp", "svg", "bmp"];
+pub(crate) const DATABASE_FILE_NAME: &str = "cache.db";
+pub(crate) const SUPPORTED_IMAGE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "webp", "svg", "bmp"];
+pub(crate) const AUDIO_EXTENSIONS: [&str; 4] = ["mp3", "ogg", "wav", "m4a"];
+pub(crate) const VIDEO_EXTENSIONS: [&str; 2] = ["mp4", "webm"];
+
+/// Files that can be embedded with `![[...]]` and are kept in `image_index`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaKind {
+    Image,
+    Pdf,
+    Audio,
+    Video,
+}
+
+impl MediaKind {
+    pub fn of(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        let ext = ext.as_str();
+        if SUPPORTED_IMAGE_EXTENSIONS.contains(&ext) {
+            Some(MediaKind::Image)
+        } else if ext == "pdf" {
+            Some(MediaKind::Pdf)
+        } else if AUDIO_EXTENSIONS.contains(&ext) {
+            Some(MediaKind::Audio)
+        } else if VIDEO_EXTENSIONS.contains(&ext) {
+            Some(MediaKind::Video)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MediaKind::Image => "image",
+            MediaKind::Pdf => "pdf",
+            MediaKind::Audio => "audio",
+            MediaKind::Video => "video",
+        }
+    }
+}
 
 // Note: RwLock cannot be used because rusqlite::Connection is not Sync
 // Using Mutex for thread-safe access
@@ -39,7 +87,7 @@ fn configure_database_performance(connection: &Connection) {
         .expect("Failed to configure database performance");
 }
 
-fn ensure_schema_exists(connection: &Connection) {
+pub(crate) fn ensure_schema_exists(connection: &Connection) {
     connection
         .execute(
             "CREATE TABLE IF NOT EXISTS image_index (
@@ -68,6 +116,44 @@ fn ensure_schema_exists(connection: &Connection) {
             [],
         )
         .expect("Failed to create index on file_name");
+
+    connection
+        .execute(
+            "CREATE INDEX IF NOT EXISTS idx_file_name_nocase ON image_index(file_name COLLATE NOCASE)",
+            [],
+        )
+        .expect("Failed to create case-insensitive index on file_name");
+
+    connection
+        .execute(
+            "CREATE TABLE IF NOT EXISTS link_index (
+            source_path TEXT NOT NULL,
+            target TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            line INTEGER
+        )",
+            [],
+        )
+        .expect("Failed to create link_index table");
+
+    connection
+        .execute(
+            "CREATE INDEX IF NOT EXISTS idx_link_source ON link_index(source_path)",
+            [],
+        )
+        .expect("Failed to create index on source_path");
+
+    connection
+        .execute(
+            "CREATE TABLE IF NOT EXISTS remote_images (
+            url TEXT PRIMARY KEY,
+            file_name TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            last_used INTEGER NOT NULL
+        )",
+            [],
+        )
+        .expect("Failed to create remote_images table");
 }
 
 fn has_folder_changed(connection: &Connection, path: &str) -> bool {
@@ -95,20 +181,167 @@ fn has_folder_changed(connection: &Connection, path: &str) -> bool {
     }
 }
 
-fn is_image_file(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| SUPPORTED_IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
-        .unwrap_or(false)
+pub(crate) fn is_media_file(path: &Path) -> bool {
+    MediaKind::of(path).is_some()
+}
+
+/// Escapes `%`, `_` and the backslash used as the `LIKE` escape character.
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
-fn fetch_path_from_db(connection: &Connection, file_name: &str) -> Option<String> {
-    let decode_name = decode(file_name).unwrap_or(std::borrow::Cow::Borrowed(file_name));
-    let search_pattern = format!("%{}", decode_name);
-    let sql = "SELECT full_path FROM image_index WHERE full_path LIKE ? LIMIT 1";
+/// Indexed images named like `asset_name`. A bare name matches the file name
+/// exactly; a name with folders (`img/a.png`) must also match as a path suffix.
+fn fetch_candidates(connection: &Connection, asset_name: &str) -> Vec<String> {
+    let name = asset_name.trim_start_matches("./").trim_start_matches('/');
+    let (subpath, file_name) = match name.rsplit_once('/') {
+        Some((_, file_name)) => (format!("%/{}", escape_like(name)), file_name),
+        None => (String::new(), name),
+    };
+    // Obsidian matches names case-insensitively: `![[Chart.PNG]]` finds `chart.png`
+    let sql = "SELECT full_path FROM image_index
+               WHERE file_name = ?1 COLLATE NOCASE
+                 AND (?2 = '' OR full_path LIKE ?2 ESCAPE '\\')";
+
+    let Ok(mut statement) = connection.prepare_cached(sql) else {
+        return Vec::new();
+    };
+    statement
+        .query_map(params![file_name, subpath], |row| row.get(0))
+        .map(|rows| rows.filter_map(|row| row.ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Folders between `from` and `to`, counting both the way up and down.
+fn folder_distance(from: &Path, to: &Path) -> usize {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    from.components().count() + to.components().count() - 2 * common
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ImageMatch {
+    pub path: Option<String>,
+    /// Other images the name could refer to, best first. Empty unless the
+    /// name is ambiguous.
+    pub alternatives: Vec<String>,
+}
 
+/// How image names that are not next to the note are looked up in `image_index`.
+#[derive(Debug, Clone, Default)]
+pub struct ImageLookup {
+    pub search_paths: Vec<PathBuf>,
+    pub attachment_folder: Option<String>,
+    /// Obsidian vaults the search paths belong to; their `attachmentFolderPath`
+    /// applies when `attachment_folder` is not set.
+    pub vaults: Vec<VaultSettings>,
+}
+
+impl ImageLookup {
+    pub fn from_config(config: &Config) -> Self {
+        let search_paths: Vec<PathBuf> = config.search_paths.iter().map(PathBuf::from).collect();
+        let mut vaults: Vec<VaultSettings> = Vec::new();
+        for root in search_paths.iter().filter_map(|path| find_vault(path)) {
+            if !vaults.iter().any(|vault| vault.root == root) {
+                vaults.push(VaultSettings::load(&root));
+            }
+        }
+
+        ImageLookup {
+            search_paths,
+            attachment_folder: config.attachment_folder.clone(),
+            vaults,
+        }
+    }
+
+    fn attachment_dirs(&self, note_dir: &Path) -> Vec<PathBuf> {
+        let Some(folder) = self.attachment_folder.as_deref().filter(|f| !f.is_empty()) else {
+            return self
+                .vaults
+                .iter()
+                .filter(|vault| note_dir.starts_with(&vault.root))
+                .max_by_key(|vault| vault.root.components().count())
+                .and_then(|vault| vault.attachment_dir(note_dir))
+                .map(|dir| normalize_path(&dir))
+                .into_iter()
+                .collect();
+        };
+        if folder.starts_with("./") || folder.starts_with("../") {
+            vec![normalize_path(&note_dir.join(folder))]
+        } else if Path::new(folder).is_absolute() {
+            vec![normalize_path(Path::new(folder))]
+        } else {
+            self.search_paths
+                .iter()
+                .map(|root| normalize_path(&root.join(folder)))
+                .collect()
+        }
+    }
+
+    /// Resolves `asset_name` as written in the note at `current_file_path`:
+    /// relative to the note first, then by name through `image_index`. Several
+    /// indexed matches are ranked deterministically: the attachment folder
+    /// first, then the fewest folders away from the note, then by path.
+    pub fn resolve(
+        &self,
+        connection: &Connection,
+        current_file_path: &str,
+        asset_name: &str,
+    ) -> ImageMatch {
+        let decoded = decode(asset_name).unwrap_or(std::borrow::Cow::Borrowed(asset_name));
+
+        let relative = resolve_relative_path(current_file_path, asset_name)
+            .or_else(|| resolve_relative_path(current_file_path, &decoded));
+        if relative.is_some() {
+            return ImageMatch {
+                path: relative,
+                alternatives: Vec::new(),
+            };
+        }
+
+        let note_dir = Path::new(current_file_path)
+            .parent()
+            .unwrap_or(Path::new(""));
+        let attachment_dirs = self.attachment_dirs(note_dir);
+
+        let file_name = decoded.rsplit('/').next().unwrap_or(&decoded);
+        let mut candidates = fetch_candidates(connection, &decoded);
+        candidates.sort_by_cached_key(|candidate| {
+            let path = Path::new(candidate);
+            let exact_case = path.file_name().is_some_and(|name| name == file_name);
+            let in_attachments = attachment_dirs.iter().any(|dir| path.starts_with(dir));
+            let distance = folder_distance(note_dir, path.parent().unwrap_or(Path::new("")));
+            (!exact_case, !in_attachments, distance, candidate.clone())
+        });
+
+        let mut candidates = candidates.into_iter();
+        ImageMatch {
+            path: candidates.next(),
+            alternatives: candidates.collect(),
+        }
+    }
+}
+
+/// Row count, last row id and total path length of `image_index`, which
+/// change with nearly every rebuild that adds, moves or drops an image.
+pub(crate) fn index_version(connection: &Connection) -> Option<(i64, i64, i64)> {
     connection
-        .query_row(sql, [search_pattern], |row| row.get(0))
+        .query_row(
+            "SELECT COUNT(*), COALESCE(MAX(rowid), 0), COALESCE(SUM(LENGTH(full_path)), 0)
+             FROM image_index",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
         .ok()
 }
 
@@ -121,11 +354,25 @@ pub async fn rebuild_index(
 
     let mut connection = state.0.lock().unwrap();
     println!("Lock acquired in: {:?}", now.elapsed());
-    let config = get_config(&app_handle).unwrap();
+    index_search_paths(&app_handle, &mut connection)
+}
+
+/// Indexes the images and links of every search path that changed since it
+/// was last indexed.
+pub(crate) fn index_search_paths(
+    app_handle: &AppHandle,
+    connection: &mut Connection,
+) -> Result<(), String> {
+    let config = load_config(app_handle)?;
 
     for base_path in config.search_paths {
+        if !Path::new(&base_path).is_dir() {
+            println!("Folder {} skip: Not found", base_path);
+            continue;
+        }
+
         let check_time = Instant::now();
-        if !has_folder_changed(&connection, &base_path) {
+        if !has_folder_changed(connection, &base_path) {
             println!("Folder {} skip: No changes detected", base_path);
             println!("Folder metadata check took: {:?}", check_time.elapsed());
             continue;
@@ -134,6 +381,7 @@ pub async fn rebuild_index(
         let transaction = connection.transaction().map_err(|e| e.to_string())?;
         println!("Indexing folder: {}", base_path);
         index_directory_images(&transaction, &base_path)?;
+        index_directory_links(&transaction, &base_path)?;
 
         let metadata = std::fs::metadata(&base_path).map_err(|e| e.to_string())?;
         let current_mtime = metadata
@@ -157,12 +405,17 @@ pub async fn rebuild_index(
     Ok(())
 }
 
-fn index_directory_images(transaction: &Transaction, base_path: &str) -> Result<(), String> {
+pub(crate) fn index_directory_images(
+    transaction: &Transaction,
+    base_path: &str,
+) -> Result<(), String> {
+    // Quarantined images must not resolve, or they would never look unused
     let image_entries = WalkDir::new(base_path)
         .follow_links(true)
         .into_iter()
+        .filter_entry(|entry| entry.file_name() != QUARANTINE_DIR)
         .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().is_file() && is_image_file(entry.path()));
+        .filter(|entry| entry.file_type().is_file() && is_media_file(entry.path()));
 
     for entry in image_entries {
         upsert_image_record(transaction, entry.path())?;
@@ -171,7 +424,7 @@ fn index_directory_images(transaction: &Transaction, base_path: &str) -> Result<
     Ok(())
 }
 
-fn upsert_image_record(transaction: &Transaction, path: &Path) -> Result<(), String> {
+pub(crate) fn upsert_image_record(transaction: &Transaction, path: &Path) -> Result<(), String> {
     let file_name = path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
@@ -198,40 +451,210 @@ fn upsert_image_record(transaction: &Transaction, path: &Path) -> Result<(), Str
 // Single image resolution (kept for backward compatibility)
 #[tauri::command]
 pub fn resolve_image_path(
+    app_handle: tauri::AppHandle,
     state: State<'_, DatabaseState>,
     current_file_path: String,
     asset_name: String,
 ) -> Option<String> {
-    resolve_relative_path(&current_file_path, &asset_name).or_else(|| {
-        let connection = state.0.lock().unwrap();
-        fetch_path_from_db(&connection, &asset_name)
-    })
+    if let Some(revision) = RevisionPath::parse(&current_file_path) {
+        return resolve_at_revision(&revision, &asset_name);
+    }
+
+    let lookup = load_config(&app_handle)
+        .map(|config| ImageLookup::from_config(&config))
+        .unwrap_or_default();
+    let connection = state.0.lock().unwrap();
+    let path = lookup
+        .resolve(&connection, &current_file_path, &asset_name)
+        .path;
+    if let Some(ref path) = path {
+        allow_file(Path::new(path));
+    }
+    path
 }
 
 // FIX #6: Batch image resolution to reduce N+1 IPC overhead
 // Resolves multiple image paths in a single IPC call
 #[tauri::command]
 pub fn resolve_image_paths_batch(
+    app_handle: tauri::AppHandle,
     state: State<'_, DatabaseState>,
     current_file_path: String,
     asset_names: Vec<String>,
-) -> HashMap<String, Option<String>> {
+) -> HashMap<String, ImageMatch> {
+    if let Some(revision) = RevisionPath::parse(&current_file_path) {
+        return asset_names
+            .into_iter()
+            .map(|name| {
+                let path = resolve_at_revision(&revision, &name);
+                (
+                    name,
+                    ImageMatch {
+                        path,
+                        alternatives: Vec::new(),
+                    },
+                )
+            })
+            .collect();
+    }
+
+    let lookup = load_config(&app_handle)
+        .map(|config| ImageLookup::from_config(&config))
+        .unwrap_or_default();
     let connection = state.0.lock().unwrap();
 
     asset_names
         .into_iter()
         .map(|name| {
-            let resolved = resolve_relative_path(&current_file_path, &name)
-                .or_else(|| fetch_path_from_db(&connection, &name));
+            let resolved = lookup.resolve(&connection, &current_file_path, &name);
+            if let Some(ref path) = resolved.path {
+                allow_file(Path::new(path));
+            }
             (name, resolved)
         })
         .collect()
 }
 
-fn resolve_relative_path(current_file_path: &str, asset_name: &str) -> Option<String> {
+/// Images and media of a historical version come from the same revision,
+/// inlined as a data URL since they may not exist in the working copy.
+pub(crate) fn resolve_at_revision(revision: &RevisionPath, asset_name: &str) -> Option<String> {
+    let bytes = revision.read_asset(asset_name)?;
+    let mime = media_mime_type(Path::new(asset_name))?;
+    Some(format!("data:{};base64,{}", mime, BASE64.encode(bytes)))
+}
+
+pub fn media_mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let mime = match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+pub(crate) fn resolve_relative_path(current_file_path: &str, asset_name: &str) -> Option<String> {
     Path::new(current_file_path)
         .parent()
         .map(|parent| parent.join(asset_name))
         .filter(|path| path.exists())
         .map(|path| path.to_string_lossy().into_owned())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+    use std::fs;
+
+    #[test]
+    fn test_lookup_ranks_matches_deterministically() {
+        let scratch = scratch_dir();
+        let vault = scratch.path().to_path_buf();
+        for dir in ["notes/project", "notes/other", "attachments", "img"] {
+            fs::create_dir_all(vault.join(dir)).unwrap();
+        }
+        for image in [
+            "notes/other/chart.png",
+            "attachments/chart.png",
+            "img/chart.png",
+            "img/foo-bar.png",
+            "img/100%_a.png",
+            "img/100x_a.png",
+        ] {
+            fs::write(vault.join(image), "").unwrap();
+        }
+
+        let mut connection = Connection::open_in_memory().unwrap();
+        ensure_schema_exists(&connection);
+        let transaction = connection.transaction().unwrap();
+        index_directory_images(&transaction, &vault.to_string_lossy()).unwrap();
+        transaction.commit().unwrap();
+
+        let note = vault.join("notes/project/plan.md");
+        let note = note.to_string_lossy();
+        let path = |p: &str| vault.join(p).to_string_lossy().into_owned();
+        let mut lookup = ImageLookup {
+            search_paths: vec![vault.clone()],
+            attachment_folder: None,
+            vaults: Vec::new(),
+        };
+
+        let nearest = lookup.resolve(&connection, &note, "chart.png");
+        assert_eq!(nearest.path, Some(path("notes/other/chart.png")));
+        assert_eq!(
+            nearest.alternatives,
+            vec![path("attachments/chart.png"), path("img/chart.png")]
+        );
+
+        lookup.attachment_folder = Some("attachments".to_string());
+        let preferred = lookup.resolve(&connection, &note, "chart.png");
+        assert_eq!(preferred.path, Some(path("attachments/chart.png")));
+
+        lookup.attachment_folder = None;
+        lookup.vaults = vec![VaultSettings {
+            root: vault.clone(),
+            attachment_folder: Some("img".to_string()),
+            ..VaultSettings::default()
+        }];
+        let from_vault = lookup.resolve(&connection, &note, "chart.png");
+        assert_eq!(from_vault.path, Some(path("img/chart.png")));
+
+        let subpath = lookup.resolve(&connection, &note, "img/chart.png");
+        assert_eq!(subpath.path, Some(path("img/chart.png")));
+        assert!(subpath.alternatives.is_empty());
+
+        assert_eq!(lookup.resolve(&connection, &note, "bar.png").path, None);
+        let escaped = lookup.resolve(&connection, &note, "img/100%25_a.png");
+        assert_eq!(escaped.path, Some(path("img/100%_a.png")));
+        assert!(escaped.alternatives.is_empty());
+    }
+
+    #[test]
+    fn test_lookup_ignores_case_of_names() {
+        let scratch = scratch_dir();
+        let vault = scratch.path().to_path_buf();
+        fs::create_dir_all(vault.join("notes")).unwrap();
+        fs::create_dir_all(vault.join("img")).unwrap();
+        for image in ["img/chart.png", "img/Logo.svg", "notes/logo.svg"] {
+            fs::write(vault.join(image), "").unwrap();
+        }
+
+        let mut connection = Connection::open_in_memory().unwrap();
+        ensure_schema_exists(&connection);
+        let transaction = connection.transaction().unwrap();
+        index_directory_images(&transaction, &vault.to_string_lossy()).unwrap();
+        transaction.commit().unwrap();
+
+        let note = vault.join("notes/plan.md");
+        let note = note.to_string_lossy();
+        let path = |p: &str| vault.join(p).to_string_lossy().into_owned();
+        let lookup = ImageLookup {
+            search_paths: vec![vault.clone()],
+            attachment_folder: None,
+            vaults: Vec::new(),
+        };
+
+        let chart = lookup.resolve(&connection, &note, "Chart.PNG");
+        assert_eq!(chart.path, Some(path("img/chart.png")));
+        assert_eq!(
+            lookup.resolve(&connection, &note, "IMG/chart.png").path,
+            Some(path("img/chart.png"))
+        );
+        // A name in the same case wins over a nearer one in another case
+        let logo = lookup.resolve(&connection, &note, "Logo.svg");
+        assert_eq!(logo.path, Some(path("img/Logo.svg")));
+        assert_eq!(logo.alternatives, vec![path("notes/logo.svg")]);
+    }
+}
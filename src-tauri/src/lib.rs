@@ -1,16 +1,47 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod asset_protocol;
 mod config;
+mod config_watch;
+mod file_io;
+mod file_tree;
+mod follow;
+mod git;
 mod helper;
+mod history;
 mod image_cache;
+mod link_check;
+mod links;
 mod markdown_parse;
+mod obsidian;
+mod orphans;
+mod reading_position;
+mod refactor;
+mod remote_images;
+mod render_cache;
+mod thumbnails;
 use crate::config::set_default_env;
+use crate::config_watch::start_config_watch;
+use crate::file_io::{read_stamped, save_checked, FileStamp, SaveOutcome};
+use crate::file_tree::list_tree;
+use crate::follow::{FileChange, FollowedFile};
+use crate::git::{changed_blocks_since, git_blame_blocks, git_diff, git_repo_info, RevisionPath};
 use crate::helper::get_config_path;
 use crate::helper::get_path;
+use crate::history::{diff_version, list_versions, restore_version, History};
 use crate::image_cache::initialize_database;
 use crate::image_cache::DatabaseState;
 use crate::image_cache::{rebuild_index, resolve_image_path, resolve_image_paths_batch};
-use crate::markdown_parse::parse_markdown_to_html;
+use crate::link_check::check_links;
+use crate::markdown_parse::{
+    parse_markdown_to_html, render_markdown_chunked, BlockSpan,
+};
+use crate::obsidian::get_vault_settings;
+use crate::orphans::{find_orphan_assets, quarantine_assets, restore_assets};
+use crate::reading_position::{reading_anchor, resolve_reading_anchor};
+use crate::refactor::{move_note, rename_note};
+use crate::render_cache::RenderCache;
 use notify::{Config, RecursiveMode, Watcher};
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::fs;
 use std::path::Path;
@@ -74,11 +105,41 @@ async fn get_cache(app_handle: tauri::AppHandle, key: String) -> Result<Value, S
 }
 
 #[tauri::command]
-async fn save_file(path: String, content: String) -> Result<(), String> {
-    tokio::fs::write(&path, &content)
-        .await
-        .map_err(|e| format!("Failed to save file: {}", e))?;
-    Ok(())
+async fn save_file(
+    app_handle: tauri::AppHandle,
+    path: String,
+    content: String,
+    expected: Option<FileStamp>,
+) -> Result<SaveOutcome, String> {
+    if RevisionPath::parse(&path).is_some() {
+        return Err("Historical versions are read-only".to_string());
+    }
+
+    let history = History::for_app(&app_handle);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = Path::new(&path);
+
+        // Snapshot what is on disk first, so external edits made since the
+        // last save are part of the history too
+        if let Ok(previous) = fs::read(path) {
+            if let Err(e) = history.record(path, &previous) {
+                eprintln!("Failed to record history for {}: {}", path.display(), e);
+            }
+        }
+
+        let outcome = save_checked(path, &content, expected.as_ref())?;
+
+        if let SaveOutcome::Saved { .. } = outcome {
+            if let Err(e) = history.record(path, content.as_bytes()) {
+                eprintln!("Failed to record history for {}: {}", path.display(), e);
+            }
+        }
+
+        Ok(outcome)
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 // .invoke_handler(tauri::generate_handler![save_cache, get_cache, ...])
@@ -98,7 +159,10 @@ fn get_cli_file(app: tauri::AppHandle) -> Option<String> {
             if let Some(data) = matches.args.get("path") {
                 file_path = data.value.as_str().map(|s| s.to_string());
             } else if !matches.args.is_empty() {
-                for (_, arg) in matches.args {
+                for (name, arg) in &matches.args {
+                    if name == "rev" {
+                        continue;
+                    }
                     if let Some(val) = arg.value.as_str() {
                         // Allow any path, let frontend handle if it's a dir or file
                         file_path = Some(val.to_string());
@@ -107,14 +171,36 @@ fn get_cli_file(app: tauri::AppHandle) -> Option<String> {
                 }
             }
 
+            // `--rev <rev> <path>` or `<rev>:<path>` opens the file as it was
+            // at that revision, normalized to `<rev>:<absolute path>`.
+            let rev = matches
+                .args
+                .get("rev")
+                .and_then(|data| data.value.as_str())
+                .filter(|rev| !rev.is_empty());
+            let cwd = std::env::current_dir().unwrap_or_default();
+            let revision = file_path.as_deref().and_then(|path| match rev {
+                Some(rev) => Some(RevisionPath::with_rev(rev, Path::new(path), &cwd)),
+                None => RevisionPath::from_cli(path, &cwd),
+            });
+            if let Some(ref revision) = revision {
+                file_path = Some(revision.display());
+            }
+
             if let Some(ref path_str) = file_path {
                 if let Some(window) = app.get_webview_window("main") {
-                    let file_name = Path::new(path_str)
+                    let title_path = revision.as_ref().map(|r| r.path.as_path());
+                    let file_name = title_path
+                        .unwrap_or_else(|| Path::new(path_str))
                         .file_name()
                         .and_then(|n| n.to_str())
                         .unwrap_or(path_str);
 
-                    let _ = window.set_title(file_name);
+                    let title = match revision {
+                        Some(ref revision) => format!("{} @ {}", file_name, revision.rev),
+                        None => file_name.to_string(),
+                    };
+                    let _ = window.set_title(&title);
                 }
             }
 
@@ -124,8 +210,39 @@ fn get_cli_file(app: tauri::AppHandle) -> Option<String> {
     }
 }
 
+/// Whether `--follow` was given, to follow the file opened from the command line.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
 #[tauri::command]
-fn start_watch(window: Window, path: String) {
+fn get_cli_follow(app: tauri::AppHandle) -> bool {
+    app.cli()
+        .matches()
+        .ok()
+        .and_then(|matches| matches.args.get("follow").map(|arg| arg.value == true))
+        .unwrap_or(false)
+}
+
+/// Payload of `file-update`: the new content, and the blocks of it that
+/// differ from the version the watcher saw before.
+#[derive(Serialize, Clone)]
+struct FileUpdate {
+    content: String,
+    changed_blocks: Vec<BlockSpan>,
+    /// Stamp of `content`, for the next save; `None` while following, where
+    /// the file is expected to change under the reader.
+    stamp: Option<FileStamp>,
+}
+
+/// Payload of `file-append`, sent instead of `file-update` while following a
+/// file that is only appended to.
+#[derive(Serialize, Clone)]
+struct FileAppend {
+    text: String,
+}
+
+/// Watches the open file. With `follow`, appends to it are read and sent on
+/// their own, so a growing log is not read again in full on every write.
+#[tauri::command]
+fn start_watch(window: Window, path: String, follow: Option<bool>) {
     // Stop previous watcher thread if exists (FIX: memory leak)
     {
         let mut guard = WATCHER_STOP_FLAG.lock().unwrap();
@@ -141,6 +258,11 @@ fn start_watch(window: Window, path: String) {
         *guard = Some(stop_flag.clone());
     }
 
+    // Historical versions never change on disk.
+    if RevisionPath::parse(&path).is_some() {
+        return;
+    }
+
     std::thread::spawn(move || {
         let (tx, rx) = std::sync::mpsc::channel();
         let mut watcher = match notify::RecommendedWatcher::new(tx, Config::default()) {
@@ -164,6 +286,10 @@ fn start_watch(window: Window, path: String) {
             eprintln!("Failed to watch directory: {:?}", e);
             return;
         }
+        let mut previous = std::fs::read_to_string(&path).unwrap_or_default();
+        let mut followed = follow
+            .unwrap_or(false)
+            .then(|| FollowedFile::new(path_buf.to_path_buf(), &previous));
 
         // Use timeout-based recv to check stop flag periodically
         loop {
@@ -176,8 +302,42 @@ fn start_watch(window: Window, path: String) {
                     if event.paths.contains(&path_buf.to_path_buf()) && event.kind.is_modify() {
                         std::thread::sleep(Duration::from_millis(100));
 
-                        if let Ok(content) = std::fs::read_to_string(&path) {
-                            let _ = window.emit("file-update", content);
+                        if let Some(followed) = followed.as_mut() {
+                            match followed.poll() {
+                                Ok(FileChange::Unchanged) => {}
+                                Ok(FileChange::Appended(text)) => {
+                                    let _ = window.emit("file-append", FileAppend { text });
+                                }
+                                // Only the end is kept, so there is nothing to diff against
+                                Ok(FileChange::Rewritten(content)) => {
+                                    let _ = window.emit(
+                                        "file-update",
+                                        FileUpdate {
+                                            content,
+                                            changed_blocks: Vec::new(),
+                                            stamp: None,
+                                        },
+                                    );
+                                }
+                                Err(e) => eprintln!("Failed to read {}: {}", path, e),
+                            }
+                            continue;
+                        }
+
+                        // Several events often arrive for one write
+                        if let Ok((content, stamp)) = read_stamped(path_buf) {
+                            if content != previous {
+                                let changed_blocks = changed_blocks_since(&previous, &content);
+                                let _ = window.emit(
+                                    "file-update",
+                                    FileUpdate {
+                                        content: content.clone(),
+                                        changed_blocks,
+                                        stamp: Some(stamp),
+                                    },
+                                );
+                                previous = content;
+                            }
                         }
                     }
                 }
@@ -189,8 +349,27 @@ fn start_watch(window: Window, path: String) {
     });
 }
 
+/// A file as loaded by `read_file`.
+#[derive(Serialize)]
+struct OpenedFile {
+    content: String,
+    /// Stamp of `content`, to pass back to `save_file`; `None` for
+    /// read-only revisions.
+    stamp: Option<FileStamp>,
+}
+
 #[tauri::command]
-async fn read_file(path: String) -> Result<String, String> {
+async fn read_file(path: String) -> Result<OpenedFile, String> {
+    if let Some(revision) = RevisionPath::parse(&path) {
+        let content = tauri::async_runtime::spawn_blocking(move || revision.read())
+            .await
+            .map_err(|e| e.to_string())??;
+        return Ok(OpenedFile {
+            content,
+            stamp: None,
+        });
+    }
+
     let p = std::path::PathBuf::from(&path);
 
     // Use tokio async file I/O to avoid blocking the thread pool
@@ -201,10 +380,16 @@ async fn read_file(path: String) -> Result<String, String> {
     if !metadata.is_file() {
         return Err(format!("Lỗi: '{}' là thư mục, không phải file.", path));
     }
+    asset_protocol::allow_document(&p);
 
-    tokio::fs::read_to_string(&p)
+    let (content, stamp) = tauri::async_runtime::spawn_blocking(move || read_stamped(&p))
         .await
-        .map_err(|e| format!("Không thể đọc file: {}", e))
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Không thể đọc file: {}", e))?;
+    Ok(OpenedFile {
+        content,
+        stamp: Some(stamp),
+    })
 }
 
 #[tauri::command]
@@ -228,6 +413,14 @@ fn update_config(
     helper::save_config(&app_handle, &config)
 }
 
+#[tauri::command]
+fn validate_config(
+    app_handle: tauri::AppHandle,
+    content: Option<String>,
+) -> Result<crate::config::ConfigReport, String> {
+    helper::validate_config_source(&app_handle, content)
+}
+
 #[tauri::command]
 fn get_instance_mode(app_handle: tauri::AppHandle) -> Result<bool, String> {
     let config = helper::load_config(&app_handle)?;
@@ -257,12 +450,35 @@ fn show_window(window: tauri::Window) {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = link_check::run_cli(&args) {
+        std::process::exit(code);
+    }
+
     set_default_env();
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_cli::init())
         .plugin(tauri_plugin_opener::init())
+        .register_asynchronous_uri_scheme_protocol(
+            asset_protocol::ASSET_SCHEME,
+            |ctx, request, responder| {
+                let app_handle = ctx.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(asset_protocol::handle_request(&app_handle, &request));
+                });
+            },
+        )
+        .register_asynchronous_uri_scheme_protocol(
+            thumbnails::THUMBNAIL_SCHEME,
+            |ctx, request, responder| {
+                let app_handle = ctx.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(thumbnails::handle_request(&app_handle, &request));
+                });
+            },
+        )
         .setup(move |app| {
             // #[cfg(any(
             //     target_os = "linux",
@@ -280,6 +496,14 @@ pub fn run() {
             //     gtk_window.set_titlebar(Option::<&gtk::Widget>::None);
             // }
 
+            if let Err(e) = helper::write_config_schema(app.handle()) {
+                eprintln!("Failed to write config schema: {}", e);
+            }
+            start_config_watch(app.handle().clone());
+            if let Ok(dir) = app.path().app_cache_dir() {
+                app.manage(RenderCache::new(dir.join("renders")));
+            }
+
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 let connection = initialize_database(&handle);
@@ -294,18 +518,37 @@ pub fn run() {
             show_window,
             read_file,
             save_file,
+            list_versions,
+            diff_version,
+            restore_version,
+            git_repo_info,
+            git_diff,
+            git_blame_blocks,
             is_dir,
+            list_tree,
+            rename_note,
+            move_note,
+            check_links,
+            find_orphan_assets,
+            quarantine_assets,
+            restore_assets,
+            get_vault_settings,
             get_cli_file,
+            get_cli_follow,
             close_app,
             resolve_image_path,
             resolve_image_paths_batch,
             save_cache,
             get_cache,
+            reading_anchor,
+            resolve_reading_anchor,
             parse_markdown_to_html,
+            render_markdown_chunked,
             rebuild_index,
             get_user_css,
             get_config,
             update_config,
+            validate_config,
             get_instance_mode,
             open_new_file,
         ])
@@ -0,0 +1,438 @@
+use crate::config::parse_config;
+use crate::helper::load_config;
+use crate::image_cache::{is_media_file, DatabaseState, ImageLookup, DATABASE_FILE_NAME};
+use crate::links::{extract_links, is_note, relative_wiki_paths, resolve_wiki, Link, LinkKind};
+use crate::markdown_parse::{heading_slugs, slugify};
+use crate::obsidian::{vault_for, LinkFormat, VaultSettings};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+use walkdir::WalkDir;
+
+/// Must match `identifier` in tauri.conf.json; the headless check runs
+/// before Tauri can resolve `app_config_dir()`.
+const APP_IDENTIFIER: &str = "readtext";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    MissingImage,
+    DeadWikilink,
+    DeadLink,
+    MissingAnchor,
+}
+
+impl ProblemKind {
+    fn label(self) -> &'static str {
+        match self {
+            ProblemKind::MissingImage => "missing image",
+            ProblemKind::DeadWikilink => "dead wikilink",
+            ProblemKind::DeadLink => "dead link",
+            ProblemKind::MissingAnchor => "missing anchor",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LinkProblem {
+    pub file: String,
+    pub line: usize,
+    pub kind: ProblemKind,
+    pub target: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LinkReport {
+    pub scanned_files: usize,
+    pub problems: Vec<LinkProblem>,
+}
+
+/// Every file below the roots, indexed by file name for wikilink lookups.
+struct Vault {
+    by_name: HashMap<String, Vec<PathBuf>>,
+    notes: Vec<PathBuf>,
+    headings: HashMap<PathBuf, Vec<String>>,
+    obsidian: Vec<VaultSettings>,
+}
+
+impl Vault {
+    fn scan(roots: &[PathBuf]) -> Self {
+        let mut by_name: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        let mut notes = Vec::new();
+        let mut obsidian: Vec<VaultSettings> = Vec::new();
+
+        for root in roots {
+            if let Some(settings) = VaultSettings::for_path(root) {
+                if !obsidian.iter().any(|known| known.root == settings.root) {
+                    obsidian.push(settings);
+                }
+            }
+            let files = WalkDir::new(root)
+                .follow_links(true)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file());
+            for entry in files {
+                let path = entry.into_path();
+                if is_note(&path) {
+                    notes.push(path.clone());
+                }
+                if let Some(name) = path.file_name() {
+                    by_name
+                        .entry(name.to_string_lossy().into_owned())
+                        .or_default()
+                        .push(path);
+                }
+            }
+        }
+
+        notes.sort();
+        notes.dedup();
+        Vault {
+            by_name,
+            notes,
+            headings: HashMap::new(),
+            obsidian,
+        }
+    }
+
+    fn obsidian_settings(&self, note: &Path) -> Option<&VaultSettings> {
+        vault_for(&self.obsidian, note)
+    }
+
+    /// A wikilink, which Obsidian writes relative to the note (`../Plan`) in
+    /// the `relative` link format, and by name or from the root otherwise.
+    fn resolve_wikilink(&self, note: &Path, target: &str) -> Option<PathBuf> {
+        let target = target.trim();
+        if target.starts_with("./") || target.starts_with("../") {
+            return relative_wiki_paths(target, note)
+                .into_iter()
+                .find(|path| path.is_file());
+        }
+        self.resolve_by_name(note, target).cloned()
+    }
+
+    /// A markdown link that is not relative to the note may still resolve the
+    /// way the vault writes markdown links: by name, or from the vault root.
+    fn resolve_markdown_by_vault(&self, note: &Path, link: &Link) -> Option<PathBuf> {
+        let settings = self
+            .obsidian_settings(note)
+            .filter(|settings| settings.markdown_links_by_name())?;
+        let decoded = urlencoding::decode(&link.target).ok()?;
+        let from_root = settings.root.join(decoded.trim_start_matches('/'));
+        if from_root.is_file() {
+            return Some(from_root);
+        }
+        match settings.link_format {
+            LinkFormat::Shortest => self.resolve_by_name(note, &decoded).cloned(),
+            _ => None,
+        }
+    }
+
+    /// The file a wikilink (or, without an index, an image name) in `note`
+    /// opens.
+    fn resolve_by_name(&self, note: &Path, target: &str) -> Option<&PathBuf> {
+        let target = target.trim();
+        let with_extension = format!("{}.md", target);
+        let candidates = [target, with_extension.as_str()]
+            .into_iter()
+            .filter_map(|candidate| {
+                let name = candidate.rsplit('/').next()?;
+                self.by_name.get(name)
+            })
+            .flatten();
+        resolve_wiki(target, note, candidates)
+    }
+
+    fn headings(&mut self, note: &Path) -> &[String] {
+        self.headings.entry(note.to_path_buf()).or_insert_with(|| {
+            fs::read_to_string(note)
+                .map(|content| heading_slugs(&content))
+                .unwrap_or_default()
+        })
+    }
+
+    fn has_heading(&mut self, note: &Path, anchor: &str) -> bool {
+        let anchor = urlencoding::decode(anchor)
+            .map(|a| a.into_owned())
+            .unwrap_or_else(|_| anchor.to_string());
+        let slug = slugify(&anchor);
+        self.headings(note)
+            .iter()
+            .any(|heading| *heading == anchor || *heading == slug)
+    }
+}
+
+fn problem(note: &Path, link: &Link, kind: ProblemKind, message: String) -> LinkProblem {
+    LinkProblem {
+        file: note.to_string_lossy().into_owned(),
+        line: link.line,
+        kind,
+        target: match &link.anchor {
+            Some(anchor) => format!("{}#{}", link.target, anchor),
+            None => link.target.clone(),
+        },
+        message,
+    }
+}
+
+fn resolve_image(
+    vault: &Vault,
+    note: &Path,
+    link: &Link,
+    connection: Option<&Mutex<Connection>>,
+) -> Option<String> {
+    match connection {
+        // Locked per lookup, so the reader is not held up for the whole check
+        Some(connection) => {
+            let connection = connection.lock().unwrap();
+            ImageLookup::default()
+                .resolve(&connection, &note.to_string_lossy(), &link.target)
+                .path
+        }
+        // Without the image index, the vault's own files stand in for it
+        None => {
+            let decoded = urlencoding::decode(&link.target)
+                .map(|d| d.into_owned())
+                .unwrap_or_else(|_| link.target.clone());
+            note.parent()
+                .map(|dir| dir.join(&decoded))
+                .filter(|path| path.exists())
+                .or_else(|| vault.resolve_by_name(note, &decoded).cloned())
+                .map(|path| path.to_string_lossy().into_owned())
+        }
+    }
+}
+
+/// Checks every link in every note below `roots`: images must resolve the
+/// way the reader resolves them, wikilinks and relative links must point at
+/// existing files, and `#heading` anchors at existing headings.
+pub fn check_vault(roots: &[PathBuf], connection: Option<&Mutex<Connection>>) -> LinkReport {
+    let mut vault = Vault::scan(roots);
+    let notes = vault.notes.clone();
+    let mut problems = Vec::new();
+
+    for note in &notes {
+        let content = match fs::read_to_string(note) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", note.display(), e);
+                continue;
+            }
+        };
+
+        for link in extract_links(&content) {
+            let is_image = link.kind == LinkKind::Image
+                || (link.kind == LinkKind::Embed && is_media_file(Path::new(&link.target)));
+
+            if is_image {
+                if resolve_image(&vault, note, &link, connection).is_none() {
+                    let message = format!("Image '{}' not found", link.target);
+                    problems.push(problem(note, &link, ProblemKind::MissingImage, message));
+                }
+                continue;
+            }
+
+            let resolved = if link.target.is_empty() {
+                Some(note.clone())
+            } else if link.kind.is_wiki() {
+                let resolved = vault.resolve_wikilink(note, &link.target);
+                if resolved.is_none() {
+                    let message = format!("No note named '{}'", link.target.trim());
+                    problems.push(problem(note, &link, ProblemKind::DeadWikilink, message));
+                }
+                resolved
+            } else {
+                let resolved = link
+                    .relative_target(note)
+                    .filter(|path| path.exists())
+                    .or_else(|| vault.resolve_markdown_by_vault(note, &link));
+                if resolved.is_none() {
+                    let message = format!("'{}' does not exist", link.target);
+                    problems.push(problem(note, &link, ProblemKind::DeadLink, message));
+                }
+                resolved
+            };
+
+            if let (Some(target), Some(anchor)) = (resolved, &link.anchor) {
+                if is_note(&target) && !anchor.is_empty() && !vault.has_heading(&target, anchor) {
+                    let message = format!(
+                        "No heading '#{}' in {}",
+                        anchor,
+                        target.file_name().unwrap_or_default().to_string_lossy()
+                    );
+                    problems.push(problem(note, &link, ProblemKind::MissingAnchor, message));
+                }
+            }
+        }
+    }
+
+    LinkReport {
+        scanned_files: notes.len(),
+        problems,
+    }
+}
+
+#[tauri::command]
+pub async fn check_links(app_handle: tauri::AppHandle) -> Result<LinkReport, String> {
+    let roots: Vec<PathBuf> = load_config(&app_handle)?
+        .search_paths
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| path.is_dir())
+        .collect();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.try_state::<DatabaseState>();
+        check_vault(&roots, state.as_ref().map(|state| &state.0))
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Handles `readtext --check-links [--json] [folder...]` without opening a
+/// window. Returns the exit code, or `None` when the flag is absent.
+/// Without folders, the `search_paths` from config.json are checked.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    let args = args.get(1..).unwrap_or(&[]);
+    if !args.iter().any(|arg| arg == "--check-links") {
+        return None;
+    }
+    let json = args.iter().any(|arg| arg == "--json");
+
+    let config_dir = dirs::config_dir().map(|dir| dir.join(APP_IDENTIFIER));
+    let mut roots: Vec<PathBuf> = args
+        .iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from)
+        .collect();
+    if roots.is_empty() {
+        let config = config_dir
+            .as_ref()
+            .and_then(|dir| fs::read_to_string(dir.join("config.json")).ok())
+            .map(|source| parse_config(&source).config)
+            .unwrap_or_default();
+        roots = config.search_paths.into_iter().map(PathBuf::from).collect();
+    }
+    if roots.is_empty() {
+        eprintln!("Nothing to check: pass a folder or set search_paths in config.json");
+        return Some(2);
+    }
+    if let Some(missing) = roots.iter().find(|root| !root.is_dir()) {
+        eprintln!("'{}' is not a directory", missing.display());
+        return Some(2);
+    }
+
+    // Read-only, so a running reader is not disturbed
+    let connection = config_dir
+        .map(|dir| dir.join(DATABASE_FILE_NAME))
+        .filter(|path| path.exists())
+        .and_then(|path| Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok())
+        .map(Mutex::new);
+
+    let report = check_vault(&roots, connection.as_ref());
+
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                return Some(2);
+            }
+        }
+    } else {
+        for problem in &report.problems {
+            println!(
+                "{}:{}: {}: {}",
+                problem.file,
+                problem.line,
+                problem.kind.label(),
+                problem.message
+            );
+        }
+        println!(
+            "Checked {} notes, found {} problem(s)",
+            report.scanned_files,
+            report.problems.len()
+        );
+    }
+
+    Some(if report.problems.is_empty() { 0 } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+
+    #[test]
+    fn test_check_vault_reports_each_kind() {
+        let scratch = scratch_dir();
+        let vault = scratch.path().to_path_buf();
+        fs::create_dir_all(vault.join("img")).unwrap();
+        fs::write(vault.join("img/ok.png"), "png").unwrap();
+        fs::write(vault.join("Target.md"), "# Intro\n## Next Steps\n").unwrap();
+        fs::write(
+            vault.join("index.md"),
+            "# Index\n\
+             [[Target]] [[Target#Next Steps]] [[Target#Nope]] [[Missing]]\n\
+             ![ok](img/ok.png) ![[ok.png]] ![gone](img/gone.png)\n\
+             [t](Target.md#intro) [x](nowhere.md) [self](#index) [bad](#outro)\n",
+        )
+        .unwrap();
+
+        let report = check_vault(std::slice::from_ref(&vault), None);
+        let found: Vec<(usize, ProblemKind, &str)> = report
+            .problems
+            .iter()
+            .map(|p| (p.line, p.kind, p.target.as_str()))
+            .collect();
+
+        assert_eq!(report.scanned_files, 2);
+        assert_eq!(
+            found,
+            vec![
+                (2, ProblemKind::MissingAnchor, "Target#Nope"),
+                (2, ProblemKind::DeadWikilink, "Missing"),
+                (3, ProblemKind::MissingImage, "img/gone.png"),
+                (4, ProblemKind::DeadLink, "nowhere.md"),
+                (4, ProblemKind::MissingAnchor, "#outro"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_vault_follows_obsidian_link_format() {
+        let scratch = scratch_dir();
+        let vault = scratch.path().to_path_buf();
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        fs::create_dir_all(vault.join("daily")).unwrap();
+        fs::create_dir_all(vault.join("projects")).unwrap();
+        fs::write(vault.join("projects/Plan.md"), "# Plan\n").unwrap();
+        fs::write(
+            vault.join("daily/today.md"),
+            "[[../projects/Plan]] [plan](Plan.md) [root](projects/Plan.md) [gone](Gone.md)\n",
+        )
+        .unwrap();
+        let settings = vault.join(".obsidian/app.json");
+
+        fs::write(&settings, r#"{"useMarkdownLinks": true}"#).unwrap();
+        let report = check_vault(std::slice::from_ref(&vault), None);
+        let targets: Vec<&str> = report.problems.iter().map(|p| p.target.as_str()).collect();
+        assert_eq!(targets, vec!["Gone.md"]);
+
+        fs::write(
+            &settings,
+            r#"{"useMarkdownLinks": true, "newLinkFormat": "relative"}"#,
+        )
+        .unwrap();
+        let report = check_vault(std::slice::from_ref(&vault), None);
+        let targets: Vec<&str> = report.problems.iter().map(|p| p.target.as_str()).collect();
+        assert_eq!(targets, vec!["Plan.md", "projects/Plan.md", "Gone.md"]);
+    }
+}
@@ -0,0 +1,490 @@
+use crate::helper::normalize_path;
+use crate::markdown_parse::{markdown_options, FRONTMATTER_REGEX};
+use pulldown_cmark::{Event, Parser, Tag};
+use rusqlite::{params, Transaction};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use walkdir::WalkDir;
+
+// [[target#heading|alias]], [[#heading]] and ![[target]]
+static WIKILINK_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"(!?)\[\[([^\[\]|#^\n]*)([#^][^\[\]|\n]*)?(?:\|[^\[\]\n]*)?\]\]")
+        .expect("Invalid wikilink regex")
+});
+
+// [text](target "title") and ![alt](<target with spaces>)
+static MARKDOWN_LINK_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(
+        r#"(!?)\[(?:[^\[\]\n]|\[[^\[\]\n]*\])*\]\(\s*(<[^>\n]*>|[^\s()]+)(?:\s+(?:"[^"\n]*"|'[^'\n]*'))?\s*\)"#,
+    )
+    .expect("Invalid markdown link regex")
+});
+
+// src="..." of the tags that load media, in raw HTML
+static HTML_SRC_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(
+        r#"(?i)<(?:img|video|audio|source|iframe|embed)\b[^>]*?\ssrc\s*=\s*(?:"([^"]*)"|'([^']*)')"#,
+    )
+    .expect("Invalid HTML src regex")
+});
+
+pub const NOTE_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    /// `[[Note]]`
+    Wiki,
+    /// `![[image.png]]` or `![[Note]]`
+    Embed,
+    /// `[text](path.md)`
+    Markdown,
+    /// `![alt](path.png)`
+    Image,
+}
+
+impl LinkKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::Wiki => "wiki",
+            LinkKind::Embed => "embed",
+            LinkKind::Markdown => "markdown",
+            LinkKind::Image => "image",
+        }
+    }
+
+    pub fn is_wiki(self) -> bool {
+        matches!(self, LinkKind::Wiki | LinkKind::Embed)
+    }
+}
+
+/// A reference from a note to another note or asset.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Link {
+    pub kind: LinkKind,
+    /// Target as written, without `#heading`, `|alias` or `<>`. Markdown
+    /// link targets may still be URL-encoded.
+    pub target: String,
+    /// Heading the link points at (`#heading`), if any. Empty `target` with an
+    /// anchor means a heading in the same note.
+    pub anchor: Option<String>,
+    /// Byte range of `target` in the note.
+    #[serde(skip)]
+    pub range: Range<usize>,
+    /// Whether the markdown link target was wrapped in `<>`.
+    #[serde(skip)]
+    pub angle_brackets: bool,
+    /// 1-based line of the link.
+    pub line: usize,
+}
+
+impl Link {
+    /// The file a markdown link points to, relative to the note at `source`.
+    /// Wikilinks resolve by name instead and return `None`.
+    pub fn relative_target(&self, source: &Path) -> Option<PathBuf> {
+        if self.kind.is_wiki() || self.target.is_empty() {
+            return None;
+        }
+        let decoded = urlencoding::decode(&self.target).ok()?;
+        let dir = source.parent()?;
+        Some(normalize_path(&dir.join(decoded.as_ref())))
+    }
+}
+
+/// Obsidian-style name matching: `[[Plan]]` matches any `Plan.md`, and
+/// `[[projects/Plan]]` matches a `Plan.md` inside a `projects` folder.
+pub fn wiki_matches(target: &str, file: &Path) -> bool {
+    let target = target.trim().trim_start_matches('/');
+    if target.is_empty() {
+        return false;
+    }
+    let file = file.to_string_lossy().replace('\\', "/");
+    let with_extension = format!("{}.md", target);
+
+    [target, with_extension.as_str()]
+        .iter()
+        .any(|candidate| file == *candidate || file.ends_with(&format!("/{}", candidate)))
+}
+
+/// The file among `files` that a wikilink in `source` opens. Of several
+/// files with a matching name, Obsidian picks the one in the note's folder,
+/// then the closest to it, then the one with the shortest path.
+pub fn resolve_wiki<'a>(
+    target: &str,
+    source: &Path,
+    files: impl IntoIterator<Item = &'a PathBuf>,
+) -> Option<&'a PathBuf> {
+    let dir = source.parent().unwrap_or(Path::new(""));
+    files
+        .into_iter()
+        .filter(|file| wiki_matches(target, file))
+        .min_by_key(|file| {
+            let file: &'a PathBuf = file;
+            let folder = file.parent().unwrap_or(Path::new(""));
+            let shared = folder
+                .components()
+                .zip(dir.components())
+                .take_while(|(a, b)| a == b)
+                .count();
+            (
+                folder != dir,
+                Reverse(shared),
+                file.components().count(),
+                file.as_path(),
+            )
+        })
+}
+
+/// Files a wikilink written relative to `source` (`[[../Plan]]`, as in
+/// Obsidian's `relative` link format) may mean, or none for other links.
+pub fn relative_wiki_paths(target: &str, source: &Path) -> Vec<PathBuf> {
+    let target = target.trim();
+    if !(target.starts_with("./") || target.starts_with("../")) {
+        return Vec::new();
+    }
+    let Some(dir) = source.parent() else {
+        return Vec::new();
+    };
+    let path = normalize_path(&dir.join(target));
+    let with_extension = path.with_file_name(format!(
+        "{}.md",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    vec![path, with_extension]
+}
+
+pub(crate) fn is_external(target: &str) -> bool {
+    target.contains("://")
+        || ["mailto:", "data:", "tel:"]
+            .iter()
+            .any(|scheme| target.starts_with(scheme))
+}
+
+/// Byte ranges of code spans and code blocks, where link syntax is literal text.
+fn code_ranges(content: &str) -> Vec<Range<usize>> {
+    Parser::new_ext(content, markdown_options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every wikilink, embed, markdown link and image reference in `content`,
+/// in document order. External URLs are skipped.
+pub fn extract_links(content: &str) -> Vec<Link> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset);
+
+    let code = code_ranges(content);
+    let in_code = |offset: usize| code.iter().any(|range| range.contains(&offset));
+
+    let mut links = Vec::new();
+
+    for caps in WIKILINK_REGEX.captures_iter(content) {
+        let whole = caps.get(0).expect("match");
+        let target = caps.get(2).expect("wikilink target");
+        // `[[Note#A#B]]` points at the nested heading B; `^block` refs have no anchor
+        let anchor = caps
+            .get(3)
+            .filter(|m| m.as_str().starts_with('#'))
+            .and_then(|m| m.as_str().rsplit('#').next())
+            .map(|heading| heading.trim().to_string());
+        if in_code(whole.start()) || (target.as_str().trim().is_empty() && anchor.is_none()) {
+            continue;
+        }
+        let kind = if caps[1].is_empty() {
+            LinkKind::Wiki
+        } else {
+            LinkKind::Embed
+        };
+        links.push(Link {
+            kind,
+            target: target.as_str().to_string(),
+            anchor,
+            range: target.range(),
+            angle_brackets: false,
+            line: line_of(whole.start()),
+        });
+    }
+
+    for caps in MARKDOWN_LINK_REGEX.captures_iter(content) {
+        let whole = caps.get(0).expect("match");
+        let destination = caps.get(2).expect("link destination");
+        if in_code(whole.start()) {
+            continue;
+        }
+
+        let mut range = destination.range();
+        let angle_brackets = destination.as_str().starts_with('<');
+        if angle_brackets {
+            range = range.start + 1..range.end - 1;
+        }
+        if is_external(&content[range.clone()]) {
+            continue;
+        }
+        // Keep `#heading` out of the target so it survives a rewrite
+        let mut anchor = None;
+        if let Some(hash) = content[range.clone()].find('#') {
+            anchor = Some(content[range.start + hash + 1..range.end].to_string());
+            range.end = range.start + hash;
+        }
+
+        let target = &content[range.clone()];
+        if target.is_empty() && anchor.is_none() {
+            continue;
+        }
+        let kind = if caps[1].is_empty() {
+            LinkKind::Markdown
+        } else {
+            LinkKind::Image
+        };
+        links.push(Link {
+            kind,
+            target: target.to_string(),
+            anchor,
+            range,
+            angle_brackets,
+            line: line_of(whole.start()),
+        });
+    }
+
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+/// Byte ranges of the `src` values of the tags that load media (`<img>`,
+/// `<video>`, `<audio>`, `<source>`, and `<iframe>` or `<embed>` for PDFs) in
+/// `html`.
+pub fn html_sources(html: &str) -> Vec<Range<usize>> {
+    HTML_SRC_REGEX
+        .captures_iter(html)
+        .filter_map(|caps| caps.get(1).or_else(|| caps.get(2)))
+        .map(|value| value.range())
+        .collect()
+}
+
+/// Media `src` values in the raw HTML of `content`, outside code. External
+/// URLs are skipped.
+pub fn html_references(content: &str) -> Vec<String> {
+    Parser::new_ext(content, markdown_options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::HtmlBlock) | Event::InlineHtml(_) => Some(&content[range]),
+            _ => None,
+        })
+        .flat_map(|html| {
+            html_sources(html)
+                .into_iter()
+                .map(move |range| html[range].to_string())
+        })
+        .filter(|target| !target.is_empty() && !is_external(target))
+        .collect()
+}
+
+/// Every string value in the frontmatter of `content`, including those in
+/// lists and nested properties.
+pub fn frontmatter_strings(content: &str) -> Vec<String> {
+    fn collect(value: &serde_yaml::Value, strings: &mut Vec<String>) {
+        match value {
+            serde_yaml::Value::String(string) => strings.push(string.clone()),
+            serde_yaml::Value::Sequence(items) => {
+                items.iter().for_each(|item| collect(item, strings))
+            }
+            serde_yaml::Value::Mapping(map) => map.values().for_each(|item| collect(item, strings)),
+            _ => {}
+        }
+    }
+
+    let mut strings = Vec::new();
+    let yaml = FRONTMATTER_REGEX
+        .captures(content)
+        .and_then(|caps| serde_yaml::from_str::<serde_yaml::Value>(&caps[1]).ok());
+    if let Some(yaml) = yaml {
+        collect(&yaml, &mut strings);
+    }
+    strings
+}
+
+pub fn is_note(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| NOTE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// All files below `roots`, each listed once even if the roots overlap.
+pub fn collect_files(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = roots
+        .iter()
+        .flat_map(|root| {
+            WalkDir::new(root)
+                .follow_links(true)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path())
+        })
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// All notes below `roots`, each listed once even if the roots overlap.
+pub fn collect_notes(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut notes = collect_files(roots);
+    notes.retain(|path| is_note(path));
+    notes
+}
+
+/// Replaces the `link_index` rows of `source` with the links in `content`.
+pub fn index_note_links(
+    transaction: &Transaction,
+    source: &Path,
+    content: &str,
+) -> Result<(), String> {
+    let source = source.to_string_lossy();
+    transaction
+        .execute(
+            "DELETE FROM link_index WHERE source_path = ?1",
+            params![source],
+        )
+        .map_err(|e| e.to_string())?;
+
+    for link in extract_links(content)
+        .into_iter()
+        .filter(|l| !l.target.is_empty())
+    {
+        transaction
+            .execute(
+                "INSERT INTO link_index (source_path, target, kind, line) VALUES (?1, ?2, ?3, ?4)",
+                params![source, link.target, link.kind.as_str(), link.line as i64],
+            )
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Re-indexes the links of every note below `base_path`.
+pub fn index_directory_links(transaction: &Transaction, base_path: &str) -> Result<(), String> {
+    transaction
+        .execute(
+            "DELETE FROM link_index WHERE substr(source_path, 1, length(?1) + 1) = ?1 || '/'",
+            params![base_path.trim_end_matches('/')],
+        )
+        .map_err(|e| e.to_string())?;
+
+    for note in collect_notes(&[PathBuf::from(base_path)]) {
+        match std::fs::read_to_string(&note) {
+            Ok(content) => index_note_links(transaction, &note, &content)?,
+            Err(e) => eprintln!("Skipping links of {}: {}", note.display(), e),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let content = "# Plan\n\
+            See [[Roadmap#Q3|the roadmap]] and ![[diagram one.png]].\n\
+            A [design doc](../docs/design%20v2.md#goals) with ![alt](<img/a b.png> \"title\").\n\
+            External [site](https://example.com) and [anchor](#top).\n\
+            `[[not a link]]`\n\
+            \n\
+            ```\n\
+            [inside](code.md)\n\
+            ```\n";
+
+        let links = extract_links(content);
+        let summary: Vec<(LinkKind, &str, usize)> = links
+            .iter()
+            .map(|l| (l.kind, l.target.as_str(), l.line))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (LinkKind::Wiki, "Roadmap", 2),
+                (LinkKind::Embed, "diagram one.png", 2),
+                (LinkKind::Markdown, "../docs/design%20v2.md", 3),
+                (LinkKind::Image, "img/a b.png", 3),
+                (LinkKind::Markdown, "", 4),
+            ]
+        );
+        for link in &links {
+            assert_eq!(&content[link.range.clone()], link.target);
+        }
+        assert!(links[3].angle_brackets);
+        assert_eq!(links[0].anchor.as_deref(), Some("Q3"));
+        assert_eq!(links[2].anchor.as_deref(), Some("goals"));
+        assert_eq!(links[4].anchor.as_deref(), Some("top"));
+    }
+
+    #[test]
+    fn test_link_resolution() {
+        let source = Path::new("/vault/notes/today.md");
+        let links = extract_links("[[projects/Plan]] [d](../docs/design%20v2.md)");
+
+        assert!(wiki_matches(
+            &links[0].target,
+            Path::new("/vault/projects/Plan.md")
+        ));
+        assert!(!wiki_matches(&links[0].target, Path::new("/vault/Plan.md")));
+        assert_eq!(
+            links[1].relative_target(source),
+            Some(PathBuf::from("/vault/docs/design v2.md"))
+        );
+    }
+
+    #[test]
+    fn test_html_and_frontmatter_references() {
+        let content = "---\ncover: img/cover.png\ntags: [a, b]\nmedia:\n  - clip.webm\n---\n\
+            <p align=\"center\">\n  <img width=\"300\"\n       src=\"img/logo.svg\">\n</p>\n\
+            \n\
+            Inline <video src='demo.mp4' controls></video> and <img src=\"https://example.com/a.png\">.\n\
+            \n\
+            `<img src=\"code.png\">`\n";
+
+        assert_eq!(html_references(content), vec!["img/logo.svg", "demo.mp4"]);
+        assert_eq!(
+            frontmatter_strings(content),
+            vec!["img/cover.png", "a", "b", "clip.webm"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_wiki_prefers_closest_then_shortest() {
+        let files: Vec<PathBuf> = [
+            "/vault/archive/2023/Plan.md",
+            "/vault/notes/Plan.md",
+            "/vault/projects/Plan.md",
+            "/vault/projects/old/Plan.md",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        let resolve = |source: &str| resolve_wiki("Plan", Path::new(source), &files);
+
+        assert_eq!(resolve("/vault/notes/today.md"), Some(&files[1]));
+        assert_eq!(resolve("/vault/projects/old/x.md"), Some(&files[3]));
+        assert_eq!(resolve("/vault/projects/sub/x.md"), Some(&files[2]));
+        assert_eq!(resolve("/vault/other/x.md"), Some(&files[1]));
+        assert_eq!(
+            resolve_wiki("Missing", Path::new("/vault/a.md"), &files),
+            None
+        );
+    }
+}
@@ -1,25 +1,766 @@
-use pulldown_cmark::{html, Options, Parser};
-use std::sync::LazyLock;
+use crate::asset_protocol::{allow_file, scheme_url, ASSET_SCHEME};
+use crate::git::RevisionPath;
+use crate::helper::load_config;
+use crate::image_cache::{
+    index_version, resolve_at_revision, resolve_relative_path, DatabaseState, ImageLookup,
+    ImageMatch, MediaKind,
+};
+use crate::links::{html_sources, is_external};
+use crate::remote_images::{fetch_in_background, is_remote, RemoteImageCache};
+use crate::render_cache::{CachedRender, RenderCache, RenderOptions};
+use crate::thumbnails::{is_thumbnailable, THUMBNAIL_SCHEME};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+use pulldown_cmark_escape::{escape_href, escape_html};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use tauri::ipc::Channel;
+use tauri::Manager;
 
 // Static regex - compiled once at startup instead of every call (~1-5ms savings)
 static WIKILINK_REGEX: LazyLock<regex::Regex> =
     LazyLock::new(|| regex::Regex::new(r"!\[\[(.*?)\]\]").expect("Invalid wikilink regex pattern"));
 
-static FRONTMATTER_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+// `![[img.png|300]]`, `![[img.png|300x200]]` and `![[img.png|alt text]]`
+static EMBED_SIZE_REGEX: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"^(\d+)(?:x(\d+))?$").expect("Invalid embed size regex"));
+
+/// Starts the title of images generated from embeds that need more than a
+/// plain `<img>`; `image_html` reads it back.
+const EMBED_MARKER: char = '\u{1f}';
+
+pub(crate) static FRONTMATTER_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
     regex::Regex::new(r"(?s)^---\r?\n(.*?)\r?\n---").expect("Invalid frontmatter regex")
 });
 
+/// Rewrites the inside of `![[...]]` as a markdown image. Segments after the
+/// first `|` are a size (`300` or `300x200`) or the alt text. Sized images and
+/// PDF, audio and video embeds are marked so `image_html` can finish them.
+fn embed_to_image(inner: &str) -> String {
+    let mut segments = inner.split('|');
+    let name = segments.next().unwrap_or_default().trim();
+    let mut alt = None;
+    let mut size = None;
+    for segment in segments.map(str::trim) {
+        if EMBED_SIZE_REGEX.is_match(segment) {
+            size = Some(segment);
+        } else if !segment.is_empty() {
+            alt = Some(segment);
+        }
+    }
+
+    let alt = alt.unwrap_or(name).replace(['[', ']'], "");
+    let kind = MediaKind::of(Path::new(name)).unwrap_or(MediaKind::Image);
+    match (kind, size) {
+        (MediaKind::Image, None) => format!("![{}](<{}>)", alt, name),
+        (kind, None) => format!(
+            "![{}](<{}> \"{}{}\")",
+            alt,
+            name,
+            EMBED_MARKER,
+            kind.as_str()
+        ),
+        (kind, Some(size)) => format!(
+            "![{}](<{}> \"{}{}:{}\")",
+            alt,
+            name,
+            EMBED_MARKER,
+            kind.as_str(),
+            size
+        ),
+    }
+}
+
+/// Where an image is loaded from once resolved.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageSource {
+    pub src: String,
+    /// The original, when `src` is a downsized thumbnail.
+    pub full_src: Option<String>,
+    /// Tooltip listing the other matches of an ambiguous name.
+    pub ambiguity: Option<String>,
+}
+
+fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    let _ = escape_html(&mut escaped, value);
+    escaped
+}
+
+/// The element an image stands for: an `<img>`, or the PDF viewer or player
+/// its embed marker asks for. Without a `source`, `src` is the link as written.
+fn image_html(dest: &str, title: &str, alt: &str, source: Option<&ImageSource>) -> String {
+    let mut src = String::new();
+    let _ = match source {
+        Some(source) => escape_html(&mut src, &source.src),
+        None => escape_href(&mut src, dest),
+    };
+    let alt = escape_attribute(alt);
+
+    let (kind, size, title) = match title.strip_prefix(EMBED_MARKER) {
+        Some(marker) => match marker.split_once(':') {
+            Some((kind, size)) => (kind, Some(size), ""),
+            None => (marker, None, ""),
+        },
+        None => ("image", None, title),
+    };
+    let size = match size.and_then(|size| EMBED_SIZE_REGEX.captures(size)) {
+        Some(caps) => match caps.get(2) {
+            Some(height) => format!(" width=\"{}\" height=\"{}\"", &caps[1], height.as_str()),
+            None => format!(" width=\"{}\"", &caps[1]),
+        },
+        None => String::new(),
+    };
+
+    match kind {
+        "pdf" => format!(
+            "<iframe class=\"pdf-embed\" src=\"{}\" title=\"{}\"{}></iframe>",
+            src, alt, size
+        ),
+        "audio" => format!(
+            "<audio class=\"media-embed\" src=\"{}\" title=\"{}\" controls></audio>",
+            src, alt
+        ),
+        "video" => format!(
+            "<video class=\"media-embed\" src=\"{}\" title=\"{}\"{} controls></video>",
+            src, alt, size
+        ),
+        _ => {
+            let mut attributes = size;
+            if let Some(full_src) = source.and_then(|source| source.full_src.as_deref()) {
+                attributes.push_str(&format!(
+                    " data-full-src=\"{}\"",
+                    escape_attribute(full_src)
+                ));
+            }
+            match source.and_then(|source| source.ambiguity.as_deref()) {
+                Some(hint) => attributes.push_str(&format!(
+                    " class=\"ambiguous\" title=\"{}\"",
+                    escape_attribute(hint)
+                )),
+                None if !title.is_empty() => {
+                    attributes.push_str(&format!(" title=\"{}\"", escape_attribute(title)))
+                }
+                None => {}
+            }
+            format!("<img src=\"{}\" alt=\"{}\"{} />", src, alt, attributes)
+        }
+    }
+}
+
+/// `html` with the `src` of its media tags pointed at where `resolve` says
+/// they load from. External and unresolved sources stay as written.
+fn html_with_sources(html: &str, resolve: &mut impl FnMut(&str) -> Option<ImageSource>) -> String {
+    let mut output = html.to_string();
+    for range in html_sources(html).into_iter().rev() {
+        let dest = &html[range.clone()];
+        if dest.is_empty() || is_external(dest) {
+            continue;
+        }
+        if let Some(source) = resolve(dest) {
+            output.replace_range(range, &escape_attribute(&source.src));
+        }
+    }
+    output
+}
+
+/// Replaces every image in `events` by its final HTML, asking `resolve` where
+/// local and `http(s)` images load from. Other external links and unresolved
+/// images keep the link as written. Media in raw HTML (`<img src="...">`) is
+/// resolved too, except remote files, which load online.
+fn render_images<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+    resolve: &mut impl FnMut(&str) -> Option<ImageSource>,
+) -> Vec<Event<'a>> {
+    let mut output = Vec::new();
+    let mut image: Option<(CowStr<'a>, CowStr<'a>, String)> = None;
+    let mut nested = 0;
+    // An HTML block arrives line by line, and a tag may span several
+    let mut raw_html = String::new();
+
+    for event in events {
+        let Some((_, _, alt)) = image.as_mut() else {
+            if let Event::Html(html) = &event {
+                raw_html.push_str(html);
+                continue;
+            }
+            if !raw_html.is_empty() {
+                let html = html_with_sources(&std::mem::take(&mut raw_html), resolve);
+                output.push(Event::Html(html.into()));
+            }
+            match event {
+                Event::Start(Tag::Image {
+                    dest_url, title, ..
+                }) => image = Some((dest_url, title, String::new())),
+                Event::InlineHtml(html) => {
+                    output.push(Event::InlineHtml(html_with_sources(&html, resolve).into()))
+                }
+                event => output.push(event),
+            }
+            continue;
+        };
+
+        // Only the text of the description ends up in `alt`
+        match event {
+            Event::Start(Tag::Image { .. }) => nested += 1,
+            Event::End(TagEnd::Image) if nested > 0 => nested -= 1,
+            Event::End(TagEnd::Image) => {
+                let (dest, title, alt) = image.take().expect("inside an image");
+                let source = if dest.is_empty() || (is_external(&dest) && !is_remote(&dest)) {
+                    None
+                } else {
+                    resolve(&dest)
+                };
+                let html = image_html(&dest, &title, &alt, source.as_ref());
+                output.push(Event::InlineHtml(html.into()));
+            }
+            Event::Text(text) | Event::Code(text) => alt.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => alt.push(' '),
+            _ => {}
+        }
+    }
+    if !raw_html.is_empty() {
+        output.push(Event::Html(html_with_sources(&raw_html, resolve).into()));
+    }
+
+    output
+}
+
+/// Source bytes per chunk sent by `render_markdown_chunked`.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Put before the opening tag of a block while rendering, and turned into a
+/// `data-source-line` attribute on it afterwards.
+const SOURCE_LINE_MARKER: char = '\u{1e}';
+
+static SOURCE_LINE_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"\x1e(\d+)\x1e\n<([a-zA-Z][a-zA-Z0-9]*)").expect("Invalid source line regex")
+});
+
+/// Whether `tag` renders to a single element that can carry its source line.
+fn has_source_line(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Paragraph
+            | Tag::Heading { .. }
+            | Tag::BlockQuote(_)
+            | Tag::CodeBlock(_)
+            | Tag::List(_)
+            | Tag::Item
+            | Tag::Table(_)
+            | Tag::FootnoteDefinition(_)
+    )
+}
+
+/// Part of a long note, rendered ahead of the rest so the window can show it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RenderChunk {
+    pub index: usize,
+    pub html: String,
+    /// Source lines of the top-level blocks in this chunk, in order.
+    pub blocks: Vec<BlockSpan>,
+}
+
+/// What `render_markdown_chunked` returns once every chunk is sent.
+#[derive(Serialize, Debug)]
+pub struct RenderedNote {
+    pub chunks: usize,
+    pub outline: Vec<Heading>,
+}
+
+/// 1-based, inclusive line range of one top-level markdown block.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct BlockSpan {
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+pub(crate) fn markdown_options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options
+}
+
+/// Line numbers of byte offsets in a text whose first line is `first_line`.
+struct LineIndex {
+    starts: Vec<usize>,
+    first_line: usize,
+}
+
+impl LineIndex {
+    fn new(text: &str, first_line: usize) -> Self {
+        LineIndex {
+            starts: std::iter::once(0)
+                .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
+            first_line,
+        }
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) + self.first_line - 1
+    }
+
+    /// Lines of the block at `range` of `text`, without the blank lines that
+    /// lists and some other blocks swallow after them.
+    fn span(&self, text: &str, range: Range<usize>) -> BlockSpan {
+        let end = range.start + text[range.clone()].trim_end().len();
+        BlockSpan {
+            start_line: self.line_of(range.start),
+            end_line: self.line_of(end.saturating_sub(1).max(range.start)),
+        }
+    }
+}
+
+/// Follows the nesting of parser events to tell where top-level blocks start
+/// and end.
+#[derive(Default)]
+struct TopLevel {
+    depth: usize,
+}
+
+impl TopLevel {
+    /// Whether `event` starts a top-level block, and whether it ends one.
+    fn step(&mut self, event: &Event) -> (bool, bool) {
+        match event {
+            Event::Start(_) => {
+                self.depth += 1;
+                (self.depth == 1, false)
+            }
+            Event::End(_) => {
+                self.depth = self.depth.saturating_sub(1);
+                (false, self.depth == 0)
+            }
+            Event::Rule if self.depth == 0 => (true, true),
+            _ => (false, false),
+        }
+    }
+}
+
+/// Splits `content` into its top-level blocks (paragraphs, headings, lists,
+/// code blocks, ...). Frontmatter counts as a single block.
+pub fn top_level_blocks(content: &str) -> Vec<BlockSpan> {
+    let lines = LineIndex::new(content, 1);
+    let mut blocks = Vec::new();
+    let mut body_start = 0;
+
+    if let Some(frontmatter) = FRONTMATTER_REGEX.find(content) {
+        blocks.push(lines.span(content, frontmatter.range()));
+        body_start = frontmatter.end();
+    }
+
+    let mut top_level = TopLevel::default();
+    for (event, range) in
+        Parser::new_ext(&content[body_start..], markdown_options()).into_offset_iter()
+    {
+        if top_level.step(&event).0 {
+            let range = body_start + range.start..body_start + range.end;
+            blocks.push(lines.span(content, range));
+        }
+    }
+
+    blocks
+}
+
+/// Heading id as generated by the renderer (`applyHeadingIds`): lowercase,
+/// punctuation dropped, whitespace turned into dashes.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    let kept = text
+        .to_lowercase()
+        .trim()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || c.is_whitespace() || *c == '-')
+        .collect::<String>();
+    for c in kept.chars() {
+        let c = if c.is_whitespace() { '-' } else { c };
+        if c != '-' || !slug.ends_with('-') {
+            slug.push(c);
+        }
+    }
+    slug
+}
+
+/// Ids of every heading in `content`, in order, with `-1`, `-2`, ... added to
+/// repeated ones the same way the renderer does.
+pub fn heading_slugs(content: &str) -> Vec<String> {
+    heading_ids(content)
+        .into_iter()
+        .map(|(_, slug)| slug)
+        .collect()
+}
+
+/// Like `heading_slugs`, with the 1-based line each heading starts on.
+pub fn heading_ids(content: &str) -> Vec<(usize, String)> {
+    outline_from(content, 1)
+        .into_iter()
+        .map(|heading| (heading.line, heading.id))
+        .collect()
+}
+
+/// One entry of a note's outline.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+    pub id: String,
+    /// 1-based line the heading starts on.
+    pub line: usize,
+}
+
+/// Every heading in `content`, in order, for a text starting at `first_line`
+/// of the note. Only the start of a note can have frontmatter.
+pub fn outline_from(content: &str, first_line: usize) -> Vec<Heading> {
+    let body_start = match FRONTMATTER_REGEX.find(content) {
+        Some(frontmatter) if first_line <= 1 => frontmatter.end(),
+        _ => 0,
+    };
+    let line_index = LineIndex::new(content, first_line);
+
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut current: Option<Heading> = None;
+    for (event, range) in
+        Parser::new_ext(&content[body_start..], markdown_options()).into_offset_iter()
+    {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                current = Some(Heading {
+                    level: level as u8,
+                    text: String::new(),
+                    id: String::new(),
+                    line: line_index.line_of(body_start + range.start),
+                });
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = current.as_mut() {
+                    heading.text.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some(mut heading) = current.take() else {
+                    continue;
+                };
+                let base = slugify(&heading.text);
+                let base = if base.is_empty() {
+                    format!("heading-{}", headings.len())
+                } else {
+                    base
+                };
+
+                heading.id = base.clone();
+                let mut counter = 1;
+                while headings.iter().any(|other| other.id == heading.id) {
+                    heading.id = format!("{}-{}", base, counter);
+                    counter += 1;
+                }
+                headings.push(heading);
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
 #[tauri::command]
 pub fn parse_markdown_to_html(content: String) -> String {
+    render_html(&content, |_| None)
+}
+
+/// Renders a note with its images resolved the way `resolve_image_paths_batch`
+/// does, so the HTML comes back with final `readtext://` and `thumb://` URLs.
+/// Large images get a thumbnail sized for `pixel_ratio`. With `remote_images`
+/// on, remote images are downloaded for the next render.
+///
+/// The note goes to `on_chunk` in pieces of whole top-level blocks, so long
+/// notes show before they are fully inserted. With `first_line`, `content` is
+/// the end of the note from that line on. Returns the number of chunks sent
+/// and the outline of `content`.
+#[tauri::command]
+pub async fn render_markdown_chunked(
+    app_handle: tauri::AppHandle,
+    content: String,
+    current_file_path: Option<String>,
+    pixel_ratio: Option<f64>,
+    first_line: Option<usize>,
+    on_chunk: Channel<RenderChunk>,
+) -> Result<RenderedNote, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut sent = Ok(());
+        let render = render_note(
+            &app_handle,
+            &content,
+            current_file_path.as_deref(),
+            pixel_ratio,
+            first_line.unwrap_or(1),
+            CHUNK_BYTES,
+            |chunk| {
+                if sent.is_ok() {
+                    sent = on_chunk.send(chunk.clone()).map_err(|e| e.to_string());
+                }
+            },
+        );
+        sent.map(|()| RenderedNote {
+            chunks: render.chunks.len(),
+            outline: render.outline,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Renders a note through the `RenderCache`, so a note shown before with
+/// the same config and image index comes back without being parsed again.
+/// Each chunk goes to `on_chunk` as soon as it is ready.
+fn render_note(
+    app_handle: &tauri::AppHandle,
+    content: &str,
+    current_file_path: Option<&str>,
+    pixel_ratio: Option<f64>,
+    first_line: usize,
+    chunk_bytes: usize,
+    mut on_chunk: impl FnMut(&RenderChunk),
+) -> CachedRender {
+    let config = load_config(app_handle).unwrap_or_default();
+    // The index opens in the background; until then only relative paths
+    // resolve. It is locked per lookup, never while chunks are sent.
+    let state = app_handle.try_state::<DatabaseState>();
+    let connection = state.as_ref().map(|state| &state.0);
+    let version = connection.and_then(|connection| index_version(&connection.lock().unwrap()));
+    let options = RenderOptions {
+        current_file_path,
+        pixel_ratio: pixel_ratio.unwrap_or(1.0).ceil().max(1.0) as u32,
+        first_line,
+        chunk_bytes,
+        config: &config,
+        index_version: version,
+    };
+
+    let cache = app_handle.try_state::<RenderCache>();
+    let key = options.key(content);
+    if let Some(cached) = cache.as_ref().and_then(|cache| cache.get(&key)) {
+        for file in &cached.files {
+            allow_file(file);
+        }
+        cached.chunks.iter().for_each(&mut on_chunk);
+        return cached;
+    }
+
+    let mut render = CachedRender {
+        chunks: Vec::new(),
+        outline: outline_from(content, first_line),
+        files: Vec::new(),
+        missing: Vec::new(),
+    };
+    let mut uncached = Vec::new();
+    let mut chunks = Vec::new();
+    resolve_and_render(
+        app_handle,
+        content,
+        &options,
+        connection,
+        &mut render,
+        &mut uncached,
+        |chunk| {
+            on_chunk(&chunk);
+            chunks.push(chunk);
+        },
+    );
+    render.chunks = chunks;
+
+    // Renders made before the index opened, or while remote images are
+    // downloading, change once that is done. The end of a followed note
+    // changes with every append.
+    let settled = first_line <= 1
+        && (current_file_path.is_none_or(|path| RevisionPath::parse(path).is_some())
+            || (options.index_version.is_some() && uncached.is_empty()));
+    if let (Some(cache), true) = (cache, settled) {
+        cache.put(&key, render.clone());
+    }
+    if let (false, Some(remote)) = (
+        uncached.is_empty(),
+        RemoteImageCache::from_config(app_handle, &config),
+    ) {
+        fetch_in_background(app_handle.clone(), remote, uncached);
+    }
+    render
+}
+
+/// Renders a note with its images resolved, collecting the local `files`
+/// the HTML loads and the `missing` images into `render`, and the remote
+/// images not downloaded yet into `uncached`.
+fn resolve_and_render(
+    app_handle: &tauri::AppHandle,
+    content: &str,
+    options: &RenderOptions,
+    connection: Option<&Mutex<rusqlite::Connection>>,
+    render: &mut CachedRender,
+    uncached: &mut Vec<String>,
+    on_chunk: impl FnMut(RenderChunk),
+) {
+    let (first_line, chunk_bytes) = (options.first_line, options.chunk_bytes);
+    let Some(current_file_path) = options.current_file_path else {
+        return stream_chunks(content, first_line, |_| None, chunk_bytes, on_chunk);
+    };
+    if let Some(revision) = RevisionPath::parse(current_file_path) {
+        return stream_chunks(
+            content,
+            first_line,
+            |dest| {
+                if is_remote(dest) {
+                    return None;
+                }
+                resolve_at_revision(&revision, dest).map(|src| ImageSource {
+                    src,
+                    ..ImageSource::default()
+                })
+            },
+            chunk_bytes,
+            on_chunk,
+        );
+    }
+
+    let lookup = ImageLookup::from_config(options.config);
+    let remote = RemoteImageCache::from_config(app_handle, options.config);
+    let pixel_ratio = options.pixel_ratio;
+
+    stream_chunks(
+        content,
+        first_line,
+        |dest| {
+            // Remote images show from the cache once downloaded, and online until then
+            if is_remote(dest) {
+                let cache = remote.as_ref()?;
+                let Some(path) = connection
+                    .and_then(|connection| cache.cached(&connection.lock().unwrap(), dest))
+                else {
+                    uncached.push(dest.to_string());
+                    return None;
+                };
+                allow_file(&path);
+                let src = scheme_url(ASSET_SCHEME, &path.to_string_lossy());
+                render.files.push(path);
+                return Some(ImageSource {
+                    src,
+                    ..ImageSource::default()
+                });
+            }
+
+            let matched = match connection {
+                Some(connection) => {
+                    lookup.resolve(&connection.lock().unwrap(), current_file_path, dest)
+                }
+                None => ImageMatch {
+                    path: resolve_relative_path(current_file_path, dest),
+                    alternatives: Vec::new(),
+                },
+            };
+            let Some(path) = matched.path else {
+                if let Some(dir) = Path::new(current_file_path).parent() {
+                    render.missing.push(dir.join(dest));
+                }
+                return None;
+            };
+            allow_file(Path::new(&path));
+            render.files.push(PathBuf::from(&path));
+
+            let url = scheme_url(ASSET_SCHEME, &path);
+            let ambiguity = (!matched.alternatives.is_empty()).then(|| {
+                format!(
+                    "Ambiguous name, showing {}. Also matches:\n{}",
+                    path,
+                    matched.alternatives.join("\n")
+                )
+            });
+            Some(if is_thumbnailable(Path::new(&path)) {
+                ImageSource {
+                    src: format!(
+                        "{}?dpr={}",
+                        scheme_url(THUMBNAIL_SCHEME, &path),
+                        pixel_ratio
+                    ),
+                    full_src: Some(url),
+                    ambiguity,
+                }
+            } else {
+                ImageSource {
+                    src: url,
+                    full_src: None,
+                    ambiguity,
+                }
+            })
+        },
+        chunk_bytes,
+        on_chunk,
+    )
+}
+
+/// Renders `content` to HTML, with frontmatter as a card and `![[...]]`
+/// embeds expanded. `resolve` maps each local image link to where it loads from.
+pub fn render_html(content: &str, resolve: impl FnMut(&str) -> Option<ImageSource>) -> String {
+    render_chunks(content, resolve, usize::MAX)
+        .into_iter()
+        .map(|chunk| chunk.html)
+        .collect()
+}
+
+/// Like `render_html`, split into chunks of whole top-level blocks covering
+/// about `chunk_bytes` of source each, with the last block in a chunk of its
+/// own. The document is parsed once, so footnotes and reference links still
+/// work across chunks.
+pub fn render_chunks(
+    content: &str,
+    resolve: impl FnMut(&str) -> Option<ImageSource>,
+    chunk_bytes: usize,
+) -> Vec<RenderChunk> {
+    render_chunks_from(content, 1, resolve, chunk_bytes)
+}
+
+/// Like `render_chunks` for the end of a note starting at `first_line`, as
+/// re-rendered when text is appended. Only the start of a note can have
+/// frontmatter, and references must be defined within `content`.
+pub fn render_chunks_from(
+    content: &str,
+    first_line: usize,
+    resolve: impl FnMut(&str) -> Option<ImageSource>,
+    chunk_bytes: usize,
+) -> Vec<RenderChunk> {
+    let mut chunks = Vec::new();
+    stream_chunks(content, first_line, resolve, chunk_bytes, |chunk| {
+        chunks.push(chunk)
+    });
+    chunks
+}
+
+/// Like `render_chunks_from`, handing each chunk to `on_chunk` as soon as its
+/// blocks are rendered.
+fn stream_chunks(
+    content: &str,
+    first_line: usize,
+    mut resolve: impl FnMut(&str) -> Option<ImageSource>,
+    chunk_bytes: usize,
+    mut on_chunk: impl FnMut(RenderChunk),
+) {
     let mut html_prefix = String::new();
-    let mut markdown_content = content.clone();
+    let mut markdown_content = content.to_string();
+    // Lines the frontmatter took, before the rest of the note
+    let mut frontmatter_lines = 0;
+    let frontmatter = if first_line <= 1 {
+        FRONTMATTER_REGEX.captures(content)
+    } else {
+        None
+    };
 
     // 1. Trích xuất Frontmatter
-    if let Some(caps) = FRONTMATTER_REGEX.captures(&content) {
+    if let Some(caps) = frontmatter {
         let yaml_str = &caps[1];
         if let Ok(yaml_val) = serde_yaml::from_str::<serde_yaml::Value>(yaml_str) {
             if let Some(map) = yaml_val.as_mapping() {
-                html_prefix.push_str("<div class=\"frontmatter-card\">");
+                html_prefix.push_str("<div class=\"frontmatter-card\" data-source-line=\"1\">");
                 html_prefix.push_str("<div class=\"frontmatter-header\">Metadata</div>");
                 html_prefix.push_str("<div class=\"frontmatter-content\">");
 
@@ -65,38 +806,166 @@ pub fn parse_markdown_to_html(content: String) -> String {
                 html_prefix.push_str("</div></div>");
 
                 // Remove frontmatter from markdown content to avoid double rendering
-                markdown_content = FRONTMATTER_REGEX.replace(&content, "").to_string();
+                markdown_content = FRONTMATTER_REGEX.replace(content, "").to_string();
+                frontmatter_lines = caps[0].matches('\n').count();
             }
         }
     }
 
     // 2. Tiền xử lý Regex (thay thế Wikilinks ![[...]])
-    let processed_content = WIKILINK_REGEX.replace_all(&markdown_content, "![$1](<$1>)");
+    let processed_content = WIKILINK_REGEX
+        .replace_all(&markdown_content, |caps: &regex::Captures| {
+            embed_to_image(&caps[1])
+        });
 
     // 2.1 Tiền xử lý regular markdown images with spaces: ![alt](image name.png) -> ![alt](<image name.png>)
     // Regex matches ![alt](path) where path contains spaces and is not already wrapped in <>
     static IMAGE_SPACE_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
-        regex::Regex::new(r"!\[(.*?)\]\(([^<>\s]+(?:\s+[^<>\s]+)+)\)")
+        regex::Regex::new(r"!\[([^\]\n]*)\]\(([^<>()\s]+(?:[ \t]+[^<>()\s]+)+)\)")
             .expect("Invalid image space regex")
     });
     let processed_content = IMAGE_SPACE_REGEX.replace_all(&processed_content, "![$1](<$2>)");
 
-    // 3. Cấu hình các tính năng
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_FOOTNOTES);
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TASKLISTS);
+    // 3. Parse sang HTML, one chunk at a time. The rewrites above keep every
+    // line in place, so only the frontmatter shifts line numbers.
+    let lines = LineIndex::new(&processed_content, frontmatter_lines + first_line.max(1));
+    let mut blocks = Vec::new();
+    if frontmatter_lines > 0 {
+        blocks.push(BlockSpan {
+            start_line: 1,
+            end_line: frontmatter_lines + 1,
+        });
+    }
+    let mut renderer = ChunkRenderer {
+        resolve: &mut resolve,
+        footnotes: HashMap::new(),
+        html_prefix,
+        index: 0,
+    };
+    let mut events = Vec::new();
+    let mut chunk_start = 0;
+    // Where the last top-level block starts in `events`
+    let mut last_block = 0;
+    let mut top_level = TopLevel::default();
+
+    for (event, range) in Parser::new_ext(&processed_content, markdown_options()).into_offset_iter()
+    {
+        let (starts, _) = top_level.step(&event);
+        if starts {
+            // Blocks so far make a chunk once they are long enough
+            if !blocks.is_empty() && range.start - chunk_start >= chunk_bytes {
+                if let Some(chunk) =
+                    renderer.render(std::mem::take(&mut events), std::mem::take(&mut blocks))
+                {
+                    on_chunk(chunk);
+                }
+                chunk_start = range.start;
+            }
+            blocks.push(lines.span(&processed_content, range.clone()));
+            last_block = events.len();
+        }
+
+        // Block elements get the line they start on, for scroll sync
+        if matches!(&event, Event::Start(tag) if has_source_line(tag)) || event == Event::Rule {
+            events.push(Event::Html(
+                format!(
+                    "{0}{1}{0}\n",
+                    SOURCE_LINE_MARKER,
+                    lines.line_of(range.start)
+                )
+                .into(),
+            ));
+        }
+
+        events.push(event);
+    }
+
+    // The last block gets a chunk of its own, so text appended to a followed
+    // note re-renders only that block
+    if blocks.len() > 1 {
+        let last_events = events.split_off(last_block);
+        let last_blocks = blocks.split_off(blocks.len() - 1);
+        if let Some(chunk) = renderer.render(events, blocks) {
+            on_chunk(chunk);
+        }
+        (events, blocks) = (last_events, last_blocks);
+    }
+    if let Some(chunk) = renderer.render(events, blocks) {
+        on_chunk(chunk);
+    }
+}
 
-    // 4. Parse sang HTML
-    let parser = Parser::new_ext(&processed_content, options);
+/// Turns the events of one chunk after another into `RenderChunk`s, keeping
+/// what pulldown-cmark's writer would keep across a whole document.
+struct ChunkRenderer<'r, R: FnMut(&str) -> Option<ImageSource>> {
+    resolve: &'r mut R,
+    /// Footnote numbers by name, in the order they were first seen.
+    footnotes: HashMap<String, usize>,
+    /// Frontmatter card, put before the first chunk.
+    html_prefix: String,
+    index: usize,
+}
 
-    let mut html_output = String::with_capacity(content.len() * 2);
-    html_output.push_str(&html_prefix);
-    html::push_html(&mut html_output, parser);
-    // println!("{}", html_output);
+impl<R: FnMut(&str) -> Option<ImageSource>> ChunkRenderer<'_, R> {
+    fn footnote_number(&mut self, name: &str) -> usize {
+        let next = self.footnotes.len() + 1;
+        *self.footnotes.entry(name.to_string()).or_insert(next)
+    }
 
-    html_output
+    /// Footnotes numbered across chunks, written the way `push_html` does.
+    fn number_footnotes<'a>(&mut self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                Event::FootnoteReference(name) => {
+                    let number = self.footnote_number(&name);
+                    Event::InlineHtml(
+                        format!(
+                            "<sup class=\"footnote-reference\"><a href=\"#{}\">{}</a></sup>",
+                            escape_attribute(&name),
+                            number
+                        )
+                        .into(),
+                    )
+                }
+                Event::Start(Tag::FootnoteDefinition(name)) => {
+                    let number = self.footnote_number(&name);
+                    Event::Html(
+                        format!(
+                            "<div class=\"footnote-definition\" id=\"{}\"><sup class=\"footnote-definition-label\">{}</sup>",
+                            escape_attribute(&name),
+                            number
+                        )
+                        .into(),
+                    )
+                }
+                Event::End(TagEnd::FootnoteDefinition) => Event::Html("</div>\n".into()),
+                event => event,
+            })
+            .collect()
+    }
+
+    fn render(&mut self, events: Vec<Event>, blocks: Vec<BlockSpan>) -> Option<RenderChunk> {
+        let events = self.number_footnotes(events);
+        let mut html_output = std::mem::take(&mut self.html_prefix);
+        html::push_html(
+            &mut html_output,
+            render_images(events.into_iter(), self.resolve).into_iter(),
+        );
+        if html_output.is_empty() {
+            return None;
+        }
+
+        let html = SOURCE_LINE_REGEX
+            .replace_all(&html_output, "<$2 data-source-line=\"$1\"")
+            .into_owned();
+        self.index += 1;
+        Some(RenderChunk {
+            index: self.index - 1,
+            html,
+            blocks,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -110,6 +979,93 @@ mod tests {
         assert!(html.contains("<img src=\"Kwin%20effect%20glass-20260220.png\""));
     }
 
+    #[test]
+    fn test_parse_wikilink_with_size_and_alt() {
+        let html = parse_markdown_to_html("![[diagram one.png|400]]".to_string());
+        assert!(html
+            .contains("<img src=\"diagram%20one.png\" alt=\"diagram one.png\" width=\"400\" />"));
+
+        let html = parse_markdown_to_html("![[a.png|Flow chart|300x200]]".to_string());
+        assert!(
+            html.contains("<img src=\"a.png\" alt=\"Flow chart\" width=\"300\" height=\"200\" />")
+        );
+    }
+
+    #[test]
+    fn test_parse_media_embeds() {
+        let html = parse_markdown_to_html(
+            "![[talk.mp3]]\n\n![[demo clip.webm|640x360]]\n\n![[paper.pdf|Paper]]".to_string(),
+        );
+        assert!(html.contains(
+            "<audio class=\"media-embed\" src=\"talk.mp3\" title=\"talk.mp3\" controls></audio>"
+        ));
+        assert!(html.contains(
+            "<video class=\"media-embed\" src=\"demo%20clip.webm\" title=\"demo clip.webm\" width=\"640\" height=\"360\" controls></video>"
+        ));
+        assert!(html
+            .contains("<iframe class=\"pdf-embed\" src=\"paper.pdf\" title=\"Paper\"></iframe>"));
+    }
+
+    #[test]
+    fn test_render_resolved_images() {
+        let markdown = "![shot](img/shot.png)\n\n![[logo.svg]]\n\n![[talk.mp3]]\n\n\
+            ![remote](https://example.com/a.png)\n\n![missing](gone.png)";
+        let html = render_html(markdown, |dest| match dest {
+            "img/shot.png" => Some(ImageSource {
+                src: "thumb://localhost/shot".to_string(),
+                full_src: Some("readtext://localhost/shot".to_string()),
+                ambiguity: None,
+            }),
+            "logo.svg" => Some(ImageSource {
+                src: "readtext://localhost/logo".to_string(),
+                full_src: None,
+                ambiguity: Some("Also <a>".to_string()),
+            }),
+            "talk.mp3" => Some(ImageSource {
+                src: "readtext://localhost/talk".to_string(),
+                ..ImageSource::default()
+            }),
+            _ => None,
+        });
+
+        assert!(html.contains(
+            "<img src=\"thumb://localhost/shot\" alt=\"shot\" data-full-src=\"readtext://localhost/shot\" />"
+        ));
+        assert!(html.contains(
+            "<img src=\"readtext://localhost/logo\" alt=\"logo.svg\" class=\"ambiguous\" title=\"Also &lt;a&gt;\" />"
+        ));
+        assert!(html.contains("<audio class=\"media-embed\" src=\"readtext://localhost/talk\""));
+        assert!(html.contains("<img src=\"https://example.com/a.png\" alt=\"remote\" />"));
+        assert!(html.contains("<img src=\"gone.png\" alt=\"missing\" />"));
+    }
+
+    #[test]
+    fn test_render_resolves_raw_html_sources() {
+        let markdown =
+            "<p align=\"center\">\n  <img width=\"300\"\n       src=\"img/shot.png\">\n</p>\n\n\
+            Inline <audio src='talk.mp3' controls></audio>, <img src=\"gone.png\"> and \
+            <img src=\"https://example.com/a.png\">.\n\n\
+            `<img src=\"img/shot.png\">`\n";
+        let html = render_html(markdown, |dest| {
+            matches!(
+                dest,
+                "img/shot.png" | "talk.mp3" | "https://example.com/a.png"
+            )
+            .then(|| ImageSource {
+                src: format!("readtext://localhost/{}", dest),
+                ..ImageSource::default()
+            })
+        });
+
+        assert!(
+            html.contains("<img width=\"300\"\n       src=\"readtext://localhost/img/shot.png\">")
+        );
+        assert!(html.contains("<audio src='readtext://localhost/talk.mp3' controls>"));
+        assert!(html.contains("<img src=\"gone.png\">"));
+        assert!(html.contains("<img src=\"https://example.com/a.png\">"));
+        assert!(html.contains("<code>&lt;img src=\"img/shot.png\"&gt;</code>"));
+    }
+
     #[test]
     fn test_parse_standard_image_with_spaces() {
         let markdown = "![test](Kwin effect glass-20260220.png)";
@@ -123,4 +1079,140 @@ mod tests {
         let html = parse_markdown_to_html(markdown.to_string());
         assert!(html.contains("<img src=\"image.png\""));
     }
+
+    #[test]
+    fn test_render_chunks() {
+        let markdown = "---\ntitle: x\n---\n# Heading\n\nFirst[^1] paragraph\nstill first.\n\n- a\n- b\n\n---\n\n[^1]: Note\n";
+        let chunks = render_chunks(markdown, |_| None, 10);
+
+        let joined: String = chunks.iter().map(|chunk| chunk.html.as_str()).collect();
+        assert_eq!(joined, render_html(markdown, |_| None));
+        assert!(chunks[0]
+            .html
+            .starts_with("<div class=\"frontmatter-card\" data-source-line=\"1\">"));
+        assert!(chunks[1]
+            .html
+            .contains("<sup class=\"footnote-reference\"><a href=\"#1\">1</a></sup>"));
+        assert!(chunks[3]
+            .html
+            .contains("class=\"footnote-definition\" id=\"1\"><sup class=\"footnote-definition-label\">1</sup>"));
+
+        let blocks: Vec<Vec<(usize, usize)>> = chunks
+            .iter()
+            .map(|chunk| {
+                chunk
+                    .blocks
+                    .iter()
+                    .map(|b| (b.start_line, b.end_line))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            blocks,
+            vec![
+                vec![(1, 3), (4, 4)],
+                vec![(6, 7)],
+                vec![(9, 10), (12, 12)],
+                vec![(14, 14)],
+            ]
+        );
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.index).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_render_chunks_from() {
+        let markdown = "# Log\n\n---\nstep: 1\n---\n\n- a\n";
+        let full = render_chunks(markdown, |_| None, usize::MAX);
+        // The end of the note from line 3 on, as re-rendered after an append
+        let tail = render_chunks_from("---\nstep: 1\n---\n\n- a\n- b\n", 3, |_| None, usize::MAX);
+
+        assert!(!tail[0].html.contains("frontmatter-card"));
+        assert!(tail[0].html.starts_with("<hr data-source-line=\"3\" />"));
+        assert!(tail[1].html.contains("<li data-source-line=\"8\">b</li>"));
+        assert_eq!(full[0].blocks[1..3], tail[0].blocks[..]);
+
+        // The last block has a chunk of its own, to re-render from on the next append
+        assert_eq!(
+            full[1].blocks,
+            vec![BlockSpan {
+                start_line: 7,
+                end_line: 7
+            }]
+        );
+        assert_eq!(
+            tail[1].blocks,
+            vec![BlockSpan {
+                start_line: 7,
+                end_line: 8
+            }]
+        );
+    }
+
+    #[test]
+    fn test_source_lines() {
+        let markdown = "---\ntitle: x\n---\n# Heading\n\n> quoted\n\n1. one\n2. two\n\n```\ncode\n```\n\n***\n\n| a |\n|---|\n| b |\n";
+        let html = render_html(markdown, |_| None);
+
+        assert!(html.contains("<h1 data-source-line=\"4\">Heading</h1>"));
+        assert!(html
+            .contains("<blockquote data-source-line=\"6\">\n<p data-source-line=\"6\">quoted</p>"));
+        assert!(html.contains("<ol data-source-line=\"8\">\n<li data-source-line=\"8\">one</li>\n<li data-source-line=\"9\">two</li>"));
+        assert!(html.contains("<pre data-source-line=\"11\"><code>code\n</code></pre>"));
+        assert!(html.contains("<hr data-source-line=\"15\" />"));
+        assert!(html.contains("<table data-source-line=\"17\">"));
+        assert!(!html.contains(SOURCE_LINE_MARKER));
+    }
+
+    #[test]
+    fn test_heading_slugs() {
+        let markdown = "---\ntitle: x\n---\n# Q&A notes\n## Setup `v2`\n## Setup v2\n## Why?\n";
+        assert_eq!(
+            heading_slugs(markdown),
+            vec!["qa-notes", "setup-v2", "setup-v2-1", "why"]
+        );
+    }
+
+    #[test]
+    fn test_outline() {
+        let outline = outline_from("---\ntitle: x\n---\n# Q&A\n\n## Setup `v2`\n", 1);
+        assert_eq!(
+            outline,
+            vec![
+                Heading {
+                    level: 1,
+                    text: "Q&A".to_string(),
+                    id: "qa".to_string(),
+                    line: 4,
+                },
+                Heading {
+                    level: 2,
+                    text: "Setup v2".to_string(),
+                    id: "setup-v2".to_string(),
+                    line: 6,
+                },
+            ]
+        );
+
+        // The end of a note has no frontmatter, and its lines follow on
+        let tail = outline_from("---\n## Log\n", 10);
+        assert_eq!(
+            tail.iter()
+                .map(|h| (h.line, h.id.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(11, "log")]
+        );
+    }
+
+    #[test]
+    fn test_top_level_blocks() {
+        let markdown = "---\ntitle: x\n---\n# Heading\n\nFirst paragraph\nstill first.\n\n- a\n- b\n\n```\ncode\n```\n";
+        let blocks: Vec<(usize, usize)> = top_level_blocks(markdown)
+            .iter()
+            .map(|b| (b.start_line, b.end_line))
+            .collect();
+        assert_eq!(blocks, vec![(1, 3), (4, 4), (6, 7), (9, 10), (12, 14)]);
+    }
 }
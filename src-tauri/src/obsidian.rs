@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder that marks the root of an Obsidian vault.
+pub const OBSIDIAN_DIR: &str = ".obsidian";
+
+/// How Obsidian writes new links (`newLinkFormat`), and so how their paths
+/// are meant to be read.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkFormat {
+    /// Just the name, with folders only when the name is ambiguous.
+    #[default]
+    Shortest,
+    /// Relative to the note.
+    Relative,
+    /// From the vault root.
+    Absolute,
+}
+
+/// The parts of `.obsidian/app.json` readtext understands.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+struct AppSettings {
+    attachment_folder_path: Option<String>,
+    new_link_format: LinkFormat,
+    use_markdown_links: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+struct AppearanceSettings {
+    enabled_css_snippets: Vec<String>,
+}
+
+/// Vault-level settings. readtext's own `Config` takes precedence wherever
+/// both have an opinion.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct VaultSettings {
+    pub root: PathBuf,
+    pub attachment_folder: Option<String>,
+    pub link_format: LinkFormat,
+    pub use_markdown_links: bool,
+    /// Names of the enabled snippets in `.obsidian/snippets`.
+    pub css_snippets: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CssSnippet {
+    pub name: String,
+    pub css: String,
+}
+
+fn read_json<T: for<'de> Deserialize<'de> + Default>(path: &Path) -> T {
+    let Ok(source) = fs::read_to_string(path) else {
+        return T::default();
+    };
+    serde_json::from_str(&source).unwrap_or_else(|e| {
+        eprintln!("Ignoring {}: {}", path.display(), e);
+        T::default()
+    })
+}
+
+/// Of the known vaults, the innermost one containing `path`.
+pub fn vault_for<'a>(vaults: &'a [VaultSettings], path: &Path) -> Option<&'a VaultSettings> {
+    vaults
+        .iter()
+        .filter(|settings| path.starts_with(&settings.root))
+        .max_by_key(|settings| settings.root.components().count())
+}
+
+/// The vault `path` belongs to: the closest folder containing `.obsidian`.
+pub fn find_vault(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.join(OBSIDIAN_DIR).is_dir())
+        .map(Path::to_path_buf)
+}
+
+impl VaultSettings {
+    /// Reads the settings of the vault rooted at `root`. Missing files and
+    /// unknown keys fall back to Obsidian's defaults.
+    pub fn load(root: &Path) -> Self {
+        let app: AppSettings = read_json(&root.join(OBSIDIAN_DIR).join("app.json"));
+        let appearance: AppearanceSettings =
+            read_json(&root.join(OBSIDIAN_DIR).join("appearance.json"));
+
+        VaultSettings {
+            root: root.to_path_buf(),
+            attachment_folder: app.attachment_folder_path,
+            link_format: app.new_link_format,
+            use_markdown_links: app.use_markdown_links,
+            css_snippets: appearance.enabled_css_snippets,
+        }
+    }
+
+    /// Settings of the vault containing `path`, if it is in one.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        find_vault(path).map(|root| Self::load(&root))
+    }
+
+    /// Where attachments of a note in `note_dir` go, following Obsidian's
+    /// rules: `./` is relative to the note, anything else to the vault root.
+    /// The vault root itself (`/` or unset) is no particular folder.
+    pub fn attachment_dir(&self, note_dir: &Path) -> Option<PathBuf> {
+        let folder = self.attachment_folder.as_deref()?.trim();
+        if folder.is_empty() || folder == "/" {
+            return None;
+        }
+        Some(match folder.strip_prefix("./") {
+            Some(relative) => note_dir.join(relative),
+            None => self.root.join(folder.trim_start_matches('/')),
+        })
+    }
+
+    /// With `useMarkdownLinks`, Obsidian writes `[text](Note.md)` in the same
+    /// shortest or absolute form as wikilinks, so such a link may not be a
+    /// path relative to the note.
+    pub fn markdown_links_by_name(&self) -> bool {
+        self.use_markdown_links && self.link_format != LinkFormat::Relative
+    }
+
+    /// Contents of the enabled CSS snippets, in the order Obsidian lists them.
+    pub fn snippets(&self) -> Vec<CssSnippet> {
+        let dir = self.root.join(OBSIDIAN_DIR).join("snippets");
+        self.css_snippets
+            .iter()
+            .filter_map(|name| {
+                let css = fs::read_to_string(dir.join(format!("{}.css", name))).ok()?;
+                Some(CssSnippet {
+                    name: name.clone(),
+                    css,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct VaultInfo {
+    #[serde(flatten)]
+    pub settings: VaultSettings,
+    pub snippets: Vec<CssSnippet>,
+}
+
+/// Obsidian settings of the vault containing `path`, with the enabled CSS
+/// snippets, or `None` outside a vault.
+#[tauri::command]
+pub async fn get_vault_settings(path: String) -> Option<VaultInfo> {
+    let settings = VaultSettings::for_path(Path::new(&path))?;
+    Some(VaultInfo {
+        snippets: settings.snippets(),
+        settings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+
+    #[test]
+    fn test_vault_settings() {
+        let scratch = scratch_dir();
+        let vault = scratch.path().to_path_buf();
+        fs::create_dir_all(vault.join(".obsidian/snippets")).unwrap();
+        fs::create_dir_all(vault.join("notes/deep")).unwrap();
+        fs::write(
+            vault.join(".obsidian/app.json"),
+            r#"{"attachmentFolderPath": "Assets", "newLinkFormat": "absolute",
+                "useMarkdownLinks": true, "alwaysUpdateLinks": true}"#,
+        )
+        .unwrap();
+        fs::write(
+            vault.join(".obsidian/appearance.json"),
+            r#"{"enabledCssSnippets": ["wide", "missing"]}"#,
+        )
+        .unwrap();
+        fs::write(vault.join(".obsidian/snippets/wide.css"), "body {}").unwrap();
+
+        let settings = VaultSettings::for_path(&vault.join("notes/deep/a.md")).unwrap();
+        assert_eq!(settings.root, vault);
+        assert_eq!(settings.link_format, LinkFormat::Absolute);
+        assert!(settings.markdown_links_by_name());
+        assert_eq!(
+            settings.attachment_dir(&vault.join("notes")),
+            Some(vault.join("Assets"))
+        );
+        assert_eq!(settings.snippets().len(), 1);
+
+        let relative = VaultSettings {
+            attachment_folder: Some("./img".to_string()),
+            ..settings
+        };
+        assert_eq!(
+            relative.attachment_dir(&vault.join("notes")),
+            Some(vault.join("notes/img"))
+        );
+        assert_eq!(VaultSettings::for_path(&std::env::temp_dir()), None);
+    }
+}
@@ -0,0 +1,524 @@
+use crate::helper::{load_config, normalize_path};
+use crate::image_cache::{is_media_file, upsert_image_record, DatabaseState, ImageLookup};
+use crate::links::{collect_notes, extract_links, frontmatter_strings, html_references, LinkKind};
+use crate::refactor::move_path;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+use walkdir::WalkDir;
+
+/// Unused images are moved here, below the search path they came from, keeping
+/// their relative location so they can be put back.
+pub const QUARANTINE_DIR: &str = ".readtext-quarantine";
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AssetInfo {
+    /// Where the image lives, or for quarantined images, where it came from.
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OrphanReport {
+    pub orphans: Vec<AssetInfo>,
+    pub total_size: u64,
+    pub quarantined: Vec<AssetInfo>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AssetMove {
+    pub from: String,
+    pub to: String,
+}
+
+fn asset_info(path: &Path, reported_as: &Path) -> AssetInfo {
+    AssetInfo {
+        path: reported_as.to_string_lossy().into_owned(),
+        size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    }
+}
+
+fn is_quarantined(path: &Path) -> bool {
+    path.components()
+        .any(|component| component.as_os_str() == QUARANTINE_DIR)
+}
+
+/// Every image, PDF or recording a note in the search paths embeds, links to,
+/// loads from raw HTML or names in its frontmatter, resolved the way the
+/// reader resolves it. The connection is locked per lookup, not for the
+/// whole scan.
+fn referenced_images(lookup: &ImageLookup, connection: &Mutex<Connection>) -> HashSet<PathBuf> {
+    let mut referenced = HashSet::new();
+
+    for note in collect_notes(&lookup.search_paths) {
+        let content = match fs::read_to_string(&note) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", note.display(), e);
+                continue;
+            }
+        };
+        let note_path = note.to_string_lossy();
+
+        // Images, and also PDFs or recordings that are only linked to
+        let links = extract_links(&content)
+            .into_iter()
+            .filter(|link| link.kind == LinkKind::Image || is_media_file(Path::new(&link.target)))
+            .map(|link| link.target);
+        // Properties such as `cover: attachments/cover.png`
+        let properties = frontmatter_strings(&content)
+            .into_iter()
+            .filter(|value| is_media_file(Path::new(value)));
+
+        for target in links.chain(html_references(&content)).chain(properties) {
+            let resolved = lookup.resolve(&connection.lock().unwrap(), &note_path, &target);
+            if let Some(path) = resolved.path {
+                referenced.insert(normalize_path(Path::new(&path)));
+            }
+        }
+    }
+
+    referenced
+}
+
+/// Indexed images in the search paths that no note references, sorted by path.
+pub fn find_orphans(
+    lookup: &ImageLookup,
+    connection: &Mutex<Connection>,
+) -> Result<Vec<AssetInfo>, String> {
+    let roots: Vec<PathBuf> = lookup
+        .search_paths
+        .iter()
+        .map(|root| normalize_path(root))
+        .collect();
+    let referenced = referenced_images(lookup, connection);
+
+    let indexed: Vec<String> = {
+        let connection = connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT full_path FROM image_index")
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        rows.filter_map(|row| row.ok()).collect()
+    };
+
+    let mut orphans: Vec<PathBuf> = indexed
+        .into_iter()
+        .map(|path| normalize_path(Path::new(&path)))
+        .filter(|path| roots.iter().any(|root| path.starts_with(root)))
+        .filter(|path| !is_quarantined(path) && path.is_file())
+        .filter(|path| !referenced.contains(path))
+        .collect();
+    orphans.sort();
+    orphans.dedup();
+
+    Ok(orphans.iter().map(|path| asset_info(path, path)).collect())
+}
+
+/// Quarantined images below `roots`, as `(quarantined, original)` paths.
+fn quarantined_files(roots: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
+    let mut files = Vec::new();
+    for root in roots {
+        let quarantine = root.join(QUARANTINE_DIR);
+        let entries = WalkDir::new(&quarantine)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file());
+        for entry in entries {
+            if let Ok(relative) = entry.path().strip_prefix(&quarantine) {
+                files.push((entry.path().to_path_buf(), root.join(relative)));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Moves files back after a failed quarantine or restore, whose index
+/// update never happened or was rolled back.
+fn undo_moves(moves: &[AssetMove]) {
+    for asset in moves.iter().rev() {
+        let (from, to) = (Path::new(&asset.from), Path::new(&asset.to));
+        let result = match from.parent() {
+            Some(parent) => fs::create_dir_all(parent).and_then(|()| move_path(to, from)),
+            None => move_path(to, from),
+        };
+        match result {
+            Ok(()) => remove_emptied_dirs(to),
+            Err(e) => eprintln!("Failed to move {} back: {}", to.display(), e),
+        }
+    }
+}
+
+/// Drops the quarantine folders that moving `file` out of them emptied;
+/// non-empty ones fail and stay.
+fn remove_emptied_dirs(file: &Path) {
+    for dir in file.ancestors().skip(1) {
+        if !is_quarantined(dir) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+/// Moves unused images into the quarantine folder of their search path and
+/// drops them from `image_index`. Only `paths` are moved when given, and only
+/// if still unused, so running it twice is harmless. If any move fails, the
+/// images moved so far are put back and nothing changes.
+pub fn quarantine_orphans(
+    lookup: &ImageLookup,
+    connection: &Mutex<Connection>,
+    paths: Option<&[String]>,
+) -> Result<Vec<AssetMove>, String> {
+    let roots: Vec<PathBuf> = lookup
+        .search_paths
+        .iter()
+        .map(|root| normalize_path(root))
+        .collect();
+    let wanted: Option<HashSet<PathBuf>> =
+        paths.map(|paths| paths.iter().map(|p| normalize_path(Path::new(p))).collect());
+
+    let orphans = find_orphans(lookup, connection)?;
+    let mut moves = Vec::new();
+
+    let result = move_orphans(&roots, orphans, wanted.as_ref(), &mut moves)
+        .and_then(|()| update_index(connection, &moves, false));
+    if let Err(e) = result {
+        undo_moves(&moves);
+        return Err(e);
+    }
+    Ok(moves)
+}
+
+fn move_orphans(
+    roots: &[PathBuf],
+    orphans: Vec<AssetInfo>,
+    wanted: Option<&HashSet<PathBuf>>,
+    moves: &mut Vec<AssetMove>,
+) -> Result<(), String> {
+    for orphan in orphans {
+        let from = PathBuf::from(&orphan.path);
+        if wanted.is_some_and(|wanted| !wanted.contains(&from)) {
+            continue;
+        }
+        let Some((root, relative)) = roots
+            .iter()
+            .find_map(|root| Some((root, from.strip_prefix(root).ok()?)))
+        else {
+            continue;
+        };
+        let to = root.join(QUARANTINE_DIR).join(relative);
+        if to.exists() {
+            eprintln!(
+                "Skipping {}: {} already exists",
+                from.display(),
+                to.display()
+            );
+            continue;
+        }
+
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        move_path(&from, &to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+        moves.push(AssetMove {
+            from: orphan.path,
+            to: to.to_string_lossy().into_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Drops the quarantined images from `image_index`, or re-indexes the
+/// restored ones, in one transaction once the files are in place.
+fn update_index(
+    connection: &Mutex<Connection>,
+    moves: &[AssetMove],
+    restored: bool,
+) -> Result<(), String> {
+    let mut connection = connection.lock().unwrap();
+    let transaction = connection.transaction().map_err(|e| e.to_string())?;
+    for asset in moves {
+        if !restored {
+            transaction
+                .execute(
+                    "DELETE FROM image_index WHERE full_path = ?1",
+                    params![asset.from],
+                )
+                .map_err(|e| e.to_string())?;
+        } else if is_media_file(Path::new(&asset.to)) {
+            upsert_image_record(&transaction, Path::new(&asset.to))?;
+        }
+    }
+    transaction.commit().map_err(|e| e.to_string())
+}
+
+/// Moves quarantined images back to where they came from and re-indexes them.
+/// `paths` are original locations; without them everything is restored.
+/// Images whose original location is taken again are left in quarantine. If
+/// any move fails, the images restored so far go back into quarantine.
+pub fn restore_quarantined(
+    roots: &[PathBuf],
+    connection: &Mutex<Connection>,
+    paths: Option<&[String]>,
+) -> Result<Vec<AssetMove>, String> {
+    let roots: Vec<PathBuf> = roots.iter().map(|root| normalize_path(root)).collect();
+    let wanted: Option<HashSet<PathBuf>> =
+        paths.map(|paths| paths.iter().map(|p| normalize_path(Path::new(p))).collect());
+
+    let mut moves = Vec::new();
+
+    let result = restore_files(&roots, wanted.as_ref(), &mut moves)
+        .and_then(|()| update_index(connection, &moves, true));
+    if let Err(e) = result {
+        undo_moves(&moves);
+        return Err(e);
+    }
+    Ok(moves)
+}
+
+fn restore_files(
+    roots: &[PathBuf],
+    wanted: Option<&HashSet<PathBuf>>,
+    moves: &mut Vec<AssetMove>,
+) -> Result<(), String> {
+    for (from, to) in quarantined_files(roots) {
+        if wanted.is_some_and(|wanted| !wanted.contains(&to)) {
+            continue;
+        }
+        if to.exists() {
+            eprintln!(
+                "Skipping {}: {} already exists",
+                from.display(),
+                to.display()
+            );
+            continue;
+        }
+
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        move_path(&from, &to).map_err(|e| format!("Failed to restore {}: {}", to.display(), e))?;
+        moves.push(AssetMove {
+            from: from.to_string_lossy().into_owned(),
+            to: to.to_string_lossy().into_owned(),
+        });
+        remove_emptied_dirs(&from);
+    }
+    Ok(())
+}
+
+fn image_lookup(app_handle: &tauri::AppHandle) -> Result<ImageLookup, String> {
+    let mut lookup = ImageLookup::from_config(&load_config(app_handle)?);
+    lookup.search_paths.retain(|path| path.is_dir());
+    Ok(lookup)
+}
+
+/// The index connection, which is opened in the background after startup.
+fn database(app_handle: &tauri::AppHandle) -> Result<tauri::State<'_, DatabaseState>, String> {
+    app_handle
+        .try_state::<DatabaseState>()
+        .ok_or_else(|| "The index is still opening; try again in a moment".to_string())
+}
+
+/// Lists images in the search paths that no note references, with their
+/// sizes, along with what is currently quarantined.
+#[tauri::command]
+pub async fn find_orphan_assets(app_handle: tauri::AppHandle) -> Result<OrphanReport, String> {
+    let lookup = image_lookup(&app_handle)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let orphans = find_orphans(&lookup, &database(&app_handle)?.0)?;
+        let quarantined = quarantined_files(&lookup.search_paths)
+            .iter()
+            .map(|(quarantined, original)| asset_info(quarantined, original))
+            .collect();
+
+        Ok(OrphanReport {
+            total_size: orphans.iter().map(|orphan| orphan.size).sum(),
+            orphans,
+            quarantined,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Moves unused images (all of them, or only `paths`) into quarantine.
+#[tauri::command]
+pub async fn quarantine_assets(
+    app_handle: tauri::AppHandle,
+    paths: Option<Vec<String>>,
+) -> Result<Vec<AssetMove>, String> {
+    let lookup = image_lookup(&app_handle)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        quarantine_orphans(&lookup, &database(&app_handle)?.0, paths.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Puts quarantined images (all of them, or only `paths`) back in place.
+#[tauri::command]
+pub async fn restore_assets(
+    app_handle: tauri::AppHandle,
+    paths: Option<Vec<String>>,
+) -> Result<Vec<AssetMove>, String> {
+    let lookup = image_lookup(&app_handle)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        restore_quarantined(
+            &lookup.search_paths,
+            &database(&app_handle)?.0,
+            paths.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+    use crate::image_cache::{ensure_schema_exists, index_directory_images};
+
+    fn scratch_vault() -> tempfile::TempDir {
+        let scratch = scratch_dir();
+        let vault = scratch.path();
+        fs::create_dir_all(vault.join("notes")).unwrap();
+        fs::create_dir_all(vault.join("attachments")).unwrap();
+        for image in ["used.png", "by-name.png", "unused.png", "old diagram.svg"] {
+            fs::write(vault.join("attachments").join(image), image).unwrap();
+        }
+        fs::write(
+            vault.join("notes/today.md"),
+            "![a](../attachments/used.png) ![[by-name.png]]\n`![[unused.png]]`\n",
+        )
+        .unwrap();
+        scratch
+    }
+
+    fn indexed_connection(vault: &Path) -> Mutex<Connection> {
+        let mut connection = Connection::open_in_memory().unwrap();
+        ensure_schema_exists(&connection);
+        let transaction = connection.transaction().unwrap();
+        index_directory_images(&transaction, &vault.to_string_lossy()).unwrap();
+        transaction.commit().unwrap();
+        Mutex::new(connection)
+    }
+
+    fn lookup(vault: &Path) -> ImageLookup {
+        ImageLookup {
+            search_paths: vec![vault.to_path_buf()],
+            attachment_folder: None,
+            vaults: Vec::new(),
+        }
+    }
+
+    fn names(assets: &[AssetInfo]) -> Vec<String> {
+        assets
+            .iter()
+            .map(|a| {
+                Path::new(&a.path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_orphans() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        let connection = indexed_connection(&vault);
+
+        let orphans = find_orphans(&lookup(&vault), &connection).unwrap();
+        assert_eq!(names(&orphans), vec!["old diagram.svg", "unused.png"]);
+        assert_eq!(orphans[1].size, "unused.png".len() as u64);
+    }
+
+    #[test]
+    fn test_html_and_frontmatter_references_are_not_orphans() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        fs::write(
+            vault.join("notes/cover.md"),
+            "---\ncover: attachments/unused.png\n---\n<img src=\"../attachments/old diagram.svg\">\n",
+        )
+        .unwrap();
+        let connection = indexed_connection(&vault);
+
+        assert!(find_orphans(&lookup(&vault), &connection)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_failed_quarantine_puts_images_back() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        fs::create_dir_all(vault.join("zz")).unwrap();
+        fs::write(vault.join("zz/last.png"), "last").unwrap();
+        let connection = indexed_connection(&vault);
+        let lookup = lookup(&vault);
+        // The quarantine folder for zz/ cannot be created
+        fs::create_dir_all(vault.join(QUARANTINE_DIR)).unwrap();
+        fs::write(vault.join(QUARANTINE_DIR).join("zz"), "").unwrap();
+
+        assert!(quarantine_orphans(&lookup, &connection, None).is_err());
+        assert!(vault.join("attachments/unused.png").exists());
+        assert!(vault.join("attachments/old diagram.svg").exists());
+        assert!(!vault.join(QUARANTINE_DIR).join("attachments").exists());
+        assert_eq!(
+            names(&find_orphans(&lookup, &connection).unwrap()),
+            vec!["old diagram.svg", "unused.png", "last.png"]
+        );
+    }
+
+    #[test]
+    fn test_quarantine_is_idempotent_and_reversible() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        let connection = indexed_connection(&vault);
+        let lookup = lookup(&vault);
+        let roots = lookup.search_paths.as_slice();
+        let unused = vault.join("attachments/unused.png");
+
+        let only = [unused.to_string_lossy().into_owned()];
+        let moved = quarantine_orphans(&lookup, &connection, Some(&only)).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert!(!unused.exists());
+        assert!(vault
+            .join(QUARANTINE_DIR)
+            .join("attachments/unused.png")
+            .exists());
+        assert!(quarantine_orphans(&lookup, &connection, Some(&only))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            names(&find_orphans(&lookup, &connection).unwrap()),
+            vec!["old diagram.svg"]
+        );
+
+        let restored = restore_quarantined(roots, &connection, None).unwrap();
+        assert_eq!(restored.len(), 1);
+        assert!(unused.exists());
+        assert!(!vault.join(QUARANTINE_DIR).exists());
+        assert!(restore_quarantined(roots, &connection, None)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            names(&find_orphans(&lookup, &connection).unwrap()),
+            vec!["old diagram.svg", "unused.png"]
+        );
+    }
+}
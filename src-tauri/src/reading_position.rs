@@ -0,0 +1,161 @@
+use crate::markdown_parse::{heading_ids, top_level_blocks, BlockSpan};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Where the reader is in a note, described by its content rather than by
+/// pixels, so it still points at the same paragraph after the note changes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReadingAnchor {
+    /// Id of the nearest heading at or above the position.
+    pub heading: Option<String>,
+    /// Index of the block among those starting at `heading`, or at the top
+    /// of the note without one.
+    pub block: usize,
+    /// Hash of the block's source with whitespace collapsed.
+    pub fingerprint: String,
+    /// How far into the block the position is, from 0 to 1.
+    pub offset: f64,
+    /// Source line of the position, used when nothing else matches.
+    pub line: f64,
+}
+
+/// A top-level block with what identifies it.
+struct Block {
+    span: BlockSpan,
+    /// Its id if the block is a heading.
+    heading: Option<String>,
+    fingerprint: String,
+}
+
+fn fingerprint(source: &str) -> String {
+    let text = source.split_whitespace().collect::<Vec<_>>().join(" ");
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn blocks(content: &str) -> Vec<Block> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut headings = heading_ids(content).into_iter().peekable();
+
+    top_level_blocks(content)
+        .into_iter()
+        .map(|span| {
+            // Headings nested in a list or quote name the block they are in
+            let mut heading = None;
+            while let Some((_, id)) = headings.next_if(|(line, _)| *line <= span.end_line) {
+                heading.get_or_insert(id);
+            }
+            let source = lines
+                .get(span.start_line - 1..span.end_line.min(lines.len()))
+                .unwrap_or_default()
+                .join("\n");
+            Block {
+                span,
+                heading,
+                fingerprint: fingerprint(&source),
+            }
+        })
+        .collect()
+}
+
+/// Position of `offset` (0 to 1) into `span`, as a fractional source line.
+fn line_in(span: &BlockSpan, offset: f64) -> f64 {
+    span.start_line as f64 + offset * (span.end_line + 1 - span.start_line) as f64
+}
+
+fn anchor_at(content: &str, line: f64) -> Option<ReadingAnchor> {
+    let blocks = blocks(content);
+    let index = blocks
+        .iter()
+        .rposition(|block| block.span.start_line as f64 <= line)
+        .unwrap_or(0);
+    let block = blocks.get(index)?;
+    let section = blocks[..=index]
+        .iter()
+        .rposition(|block| block.heading.is_some());
+
+    let lines = (block.span.end_line + 1 - block.span.start_line) as f64;
+    Some(ReadingAnchor {
+        heading: section.and_then(|start| blocks[start].heading.clone()),
+        block: index - section.unwrap_or(0),
+        fingerprint: block.fingerprint.clone(),
+        offset: ((line - block.span.start_line as f64) / lines).clamp(0.0, 1.0),
+        line,
+    })
+}
+
+/// Source line `anchor` points at in `content`: the block with the same
+/// text closest to where it was, else the block at the same place in the
+/// same section, else the same line.
+fn line_of_anchor(content: &str, anchor: &ReadingAnchor) -> f64 {
+    let blocks = blocks(content);
+    let section = anchor.heading.as_ref().and_then(|heading| {
+        blocks
+            .iter()
+            .position(|block| block.heading.as_ref() == Some(heading))
+    });
+    let expected = section.unwrap_or(0) + anchor.block;
+
+    let same_text = blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, block)| block.fingerprint == anchor.fingerprint)
+        .min_by_key(|(index, _)| index.abs_diff(expected));
+    if let Some((_, block)) = same_text {
+        return line_in(&block.span, anchor.offset);
+    }
+
+    if let Some(start) = section {
+        let end = blocks[start + 1..]
+            .iter()
+            .position(|block| block.heading.is_some())
+            .map_or(blocks.len(), |next| start + 1 + next);
+        return line_in(&blocks[expected.min(end - 1)].span, anchor.offset);
+    }
+
+    let line_count = content.lines().count().max(1) as f64;
+    anchor.line.clamp(1.0, line_count)
+}
+
+/// Anchor for the source `line` at the top of the preview of `content`, or
+/// `None` for an empty note.
+#[tauri::command]
+pub fn reading_anchor(content: String, line: f64) -> Option<ReadingAnchor> {
+    anchor_at(&content, line)
+}
+
+/// Source line in `content` where a reader at `anchor` should be, for
+/// restoring the position after the note changed on disk.
+#[tauri::command]
+pub fn resolve_reading_anchor(content: String, anchor: ReadingAnchor) -> f64 {
+    line_of_anchor(&content, &anchor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor_follows_the_paragraph() {
+        let old = "# Intro\n\nHello.\n\n# Usage\n\nFirst step.\n\nSecond step,\nin two lines.\n\nThird step.\n";
+        let anchor = anchor_at(old, 10.0).unwrap();
+        assert_eq!(anchor.heading.as_deref(), Some("usage"));
+        assert_eq!(anchor.block, 2);
+        assert_eq!(anchor.offset, 0.5);
+
+        // Lines added above move the paragraph down
+        let new = "# Intro\n\nHello.\n\nMore intro.\n\n# Usage\n\nFirst step.\n\nSecond step,\nin two lines.\n\nThird step.\n";
+        assert_eq!(line_of_anchor(new, &anchor), 12.0);
+
+        // An edited paragraph is found by its place in the section
+        let edited = "# Intro\n\nHello.\n\n# Usage\n\nFirst step.\n\nSecond step, reworded.\n\nThird step.\n";
+        assert_eq!(line_of_anchor(edited, &anchor), 9.5);
+
+        // Without the section, the line is kept
+        assert_eq!(line_of_anchor("Only\n\none\n\nsection\n", &anchor), 5.0);
+        assert_eq!(anchor_at("", 1.0), None);
+    }
+}
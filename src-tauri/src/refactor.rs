@@ -0,0 +1,693 @@
+use crate::file_io::atomic_write;
+use crate::helper::{load_config, normalize_path, relative_path};
+use crate::history::History;
+use crate::image_cache::DatabaseState;
+use crate::links::{
+    collect_files, extract_links, index_note_links, is_note, relative_wiki_paths, resolve_wiki,
+    Link,
+};
+use crate::obsidian::{vault_for, LinkFormat, VaultSettings};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LinkEdit {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FileEdits {
+    pub path: String,
+    pub edits: Vec<LinkEdit>,
+}
+
+/// What a rename or move changes. Returned both for a dry run and after
+/// the change was applied.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RenamePlan {
+    pub from: String,
+    pub to: String,
+    pub files: Vec<FileEdits>,
+    pub applied: bool,
+}
+
+struct PendingWrite {
+    path: PathBuf,
+    original: String,
+    updated: String,
+}
+
+/// Every file that moves: just `from` for a file, or everything below it for
+/// a folder.
+fn moved_files(from: &Path, to: &Path) -> HashMap<PathBuf, PathBuf> {
+    if from.is_file() {
+        return HashMap::from([(from.to_path_buf(), to.to_path_buf())]);
+    }
+
+    walkdir::WalkDir::new(from)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(from).ok()?.to_path_buf();
+            Some((entry.into_path(), to.join(relative)))
+        })
+        .collect()
+}
+
+/// `path` as wikilink text: without `.md`, unless `target` spelled it out.
+fn wiki_text(target: &str, path: String) -> String {
+    let keep_extension = Path::new(target.trim())
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("md"))
+        .unwrap_or(false);
+    if !keep_extension && is_note(Path::new(&path)) {
+        Path::new(&path)
+            .with_extension("")
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        path
+    }
+}
+
+/// New text for a wikilink that pointed at `old`: a bare name stays a bare
+/// name, a path stays a path from the same root.
+fn wiki_target(target: &str, old: &Path, new: &Path) -> String {
+    let target = target.trim();
+
+    if target.contains('/') {
+        let old_str = old.to_string_lossy().replace('\\', "/");
+        let suffix = if old_str.ends_with(&format!("/{}", target)) {
+            target.to_string()
+        } else {
+            format!("{}.md", target)
+        };
+        if let Some(root) = old_str.strip_suffix(&suffix) {
+            let new_str = new.to_string_lossy().replace('\\', "/");
+            if let Some(relative) = new_str.strip_prefix(root) {
+                return wiki_text(target, relative.to_string());
+            }
+        }
+    }
+
+    let name = new
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if target.contains('.') && !is_note(new) {
+        name
+    } else {
+        wiki_text(target, name)
+    }
+}
+
+/// New text for a wikilink written relative to the note, from a note in
+/// `source_dir` to `new`.
+fn relative_wiki_target(target: &str, source_dir: &Path, new: &Path) -> String {
+    let relative = relative_path(source_dir, new)
+        .to_string_lossy()
+        .replace('\\', "/");
+    if relative.starts_with("../") {
+        wiki_text(target, relative)
+    } else {
+        wiki_text(target, format!("./{}", relative))
+    }
+}
+
+/// `path` as markdown link text, keeping the style of the original (`<>` or
+/// `%20`).
+fn markdown_text(link: &Link, path: String) -> String {
+    if link.angle_brackets {
+        path
+    } else if link.target.contains('%') {
+        path.split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect::<Vec<_>>()
+            .join("/")
+    } else {
+        path.replace(' ', "%20")
+    }
+}
+
+/// New text for a markdown link from a note in `source_dir` to `new`,
+/// keeping the style of the original (`./` prefix, `<>` or `%20`).
+fn markdown_target(link: &Link, source_dir: &Path, new: &Path) -> String {
+    let relative = relative_path(source_dir, new)
+        .to_string_lossy()
+        .replace('\\', "/");
+    if link.target.starts_with("./") && !relative.starts_with("../") {
+        markdown_text(link, format!("./{}", relative))
+    } else {
+        markdown_text(link, relative)
+    }
+}
+
+/// The file a markdown link in `note` means when its vault writes markdown
+/// links by name or from the root (see `VaultSettings::markdown_links_by_name`)
+/// and it is not a path relative to the note.
+fn vault_markdown_file(
+    link: &Link,
+    note: &Path,
+    vault: &VaultSettings,
+    files: &[PathBuf],
+) -> Option<PathBuf> {
+    let is_file = |path: &PathBuf| files.binary_search(path).is_ok();
+    if !vault.markdown_links_by_name() || link.relative_target(note).is_some_and(|p| is_file(&p)) {
+        return None;
+    }
+    let decoded = urlencoding::decode(&link.target).ok()?;
+    let from_root = normalize_path(&vault.root.join(decoded.trim_start_matches('/')));
+    if is_file(&from_root) {
+        return Some(from_root);
+    }
+    match vault.link_format {
+        LinkFormat::Shortest => resolve_wiki(&decoded, note, files).cloned(),
+        _ => None,
+    }
+}
+
+/// New text for a markdown link written by name or from the vault root.
+fn vault_markdown_target(link: &Link, root: &Path, new: &Path) -> String {
+    let by_name = !link.target.contains('/');
+    let path = match new.strip_prefix(root) {
+        Ok(relative) if !by_name => relative.to_string_lossy().replace('\\', "/"),
+        _ => new
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    if link.target.starts_with('/') && !by_name {
+        markdown_text(link, format!("/{}", path))
+    } else {
+        markdown_text(link, path)
+    }
+}
+
+/// Rewrites the links of `note` for `moves`. Links are resolved against
+/// `files`, sorted, and the way `vault` writes them.
+fn rewrite_note(
+    note: &Path,
+    content: &str,
+    moves: &HashMap<PathBuf, PathBuf>,
+    files: &[PathBuf],
+    vault: Option<&VaultSettings>,
+) -> (String, Vec<LinkEdit>) {
+    let source_after = moves.get(note).map(PathBuf::as_path).unwrap_or(note);
+    let source_dir_after = source_after.parent().unwrap_or(Path::new("/"));
+    // Where a link relative to the note now has to point, if that changed
+    let relative_after = |resolved: PathBuf| {
+        let target_after = moves.get(&resolved).cloned().unwrap_or(resolved.clone());
+        (target_after != resolved || source_after != note).then_some(target_after)
+    };
+
+    let mut replacements = Vec::new();
+    for link in extract_links(content) {
+        let after = if link.kind.is_wiki() {
+            let relative = relative_wiki_paths(&link.target, note)
+                .into_iter()
+                .find(|path| files.binary_search(path).is_ok());
+            match relative {
+                Some(resolved) => relative_after(resolved)
+                    .map(|new| relative_wiki_target(&link.target, source_dir_after, &new)),
+                None => resolve_wiki(&link.target, note, files)
+                    .and_then(|resolved| moves.get_key_value(resolved))
+                    .map(|(old, new)| wiki_target(&link.target, old, new)),
+            }
+        } else if let Some((vault, resolved)) =
+            vault.and_then(|vault| Some((vault, vault_markdown_file(&link, note, vault, files)?)))
+        {
+            moves
+                .get(&resolved)
+                .map(|new| vault_markdown_target(&link, &vault.root, new))
+        } else {
+            link.relative_target(note)
+                .and_then(relative_after)
+                .map(|new| markdown_target(&link, source_dir_after, &new))
+        };
+
+        if let Some(after) = after.filter(|after| *after != link.target) {
+            replacements.push((link, after));
+        }
+    }
+
+    let mut updated = content.to_string();
+    for (link, after) in replacements.iter().rev() {
+        updated.replace_range(link.range.clone(), after);
+    }
+
+    let edits = replacements
+        .into_iter()
+        .map(|(link, after)| LinkEdit {
+            line: link.line,
+            before: link.target,
+            after,
+        })
+        .collect();
+    (updated, edits)
+}
+
+/// Works out every link that has to change when `from` becomes `to`,
+/// looking at all notes below `roots` and inside the moved folder.
+fn plan_relocation(
+    from: &Path,
+    to: &Path,
+    roots: &[PathBuf],
+) -> Result<(RenamePlan, Vec<PendingWrite>), String> {
+    if !from.exists() {
+        return Err(format!("'{}' does not exist", from.display()));
+    }
+    if to.exists() {
+        return Err(format!("'{}' already exists", to.display()));
+    }
+    if !to.parent().map(Path::is_dir).unwrap_or(false) {
+        return Err(format!("Folder of '{}' does not exist", to.display()));
+    }
+    if to.starts_with(from) {
+        return Err("Cannot move a folder into itself".to_string());
+    }
+
+    let moves = moved_files(from, to);
+
+    // Wikilinks are resolved against every file, as embeds can name assets
+    let mut vault_files = collect_files(roots);
+    vault_files.extend(moves.keys().cloned());
+    vault_files.sort();
+    vault_files.dedup();
+    let notes: Vec<PathBuf> = vault_files
+        .iter()
+        .filter(|path| is_note(path))
+        .cloned()
+        .collect();
+    let mut vaults: Vec<VaultSettings> = Vec::new();
+    for settings in roots
+        .iter()
+        .filter_map(|root| VaultSettings::for_path(root))
+    {
+        if !vaults.iter().any(|known| known.root == settings.root) {
+            vaults.push(settings);
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut writes = Vec::new();
+    for note in notes {
+        let content = match fs::read_to_string(&note) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", note.display(), e);
+                continue;
+            }
+        };
+
+        let (updated, edits) = rewrite_note(
+            &note,
+            &content,
+            &moves,
+            &vault_files,
+            vault_for(&vaults, &note),
+        );
+        if edits.is_empty() {
+            continue;
+        }
+        files.push(FileEdits {
+            path: note.to_string_lossy().into_owned(),
+            edits,
+        });
+        writes.push(PendingWrite {
+            path: note,
+            original: content,
+            updated,
+        });
+    }
+
+    let plan = RenamePlan {
+        from: from.to_string_lossy().into_owned(),
+        to: to.to_string_lossy().into_owned(),
+        files,
+        applied: false,
+    };
+    Ok((plan, writes))
+}
+
+pub(crate) fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        // Renaming across filesystems fails; copying works for single files
+        Err(_) if from.is_file() => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn restore_files(written: &[&PendingWrite]) {
+    for write in written.iter().rev() {
+        if let Err(e) = atomic_write(&write.path, write.original.as_bytes()) {
+            eprintln!("Failed to restore {}: {}", write.path.display(), e);
+        }
+    }
+}
+
+/// Rewrites the linking notes, then moves `from` to `to`. On any failure the
+/// notes written so far are restored and nothing is moved.
+fn apply_files(from: &Path, to: &Path, writes: &[PendingWrite]) -> Result<(), String> {
+    let mut written = Vec::new();
+    for write in writes {
+        if let Err(e) = atomic_write(&write.path, write.updated.as_bytes()) {
+            restore_files(&written);
+            return Err(format!("Failed to update {}: {}", write.path.display(), e));
+        }
+        written.push(write);
+    }
+
+    if let Err(e) = move_path(from, to) {
+        restore_files(&written);
+        return Err(format!("Failed to move {}: {}", from.display(), e));
+    }
+
+    Ok(())
+}
+
+/// Points `image_index` and `link_index` at the new location and re-indexes
+/// the links of every rewritten note, in one transaction. The connection is
+/// locked only for that.
+fn update_indexes(
+    connection: &Mutex<Connection>,
+    from: &Path,
+    to: &Path,
+    writes: &[PendingWrite],
+) -> Result<(), String> {
+    let mut connection = connection.lock().unwrap();
+    let transaction = connection.transaction().map_err(|e| e.to_string())?;
+    let from_str = from.to_string_lossy();
+    let to_str = to.to_string_lossy();
+    let to_name = to
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    transaction
+        .execute(
+            "UPDATE image_index SET full_path = ?2 || substr(full_path, length(?1) + 1)
+             WHERE full_path = ?1 OR substr(full_path, 1, length(?1) + 1) = ?1 || '/'",
+            params![from_str, to_str],
+        )
+        .map_err(|e| e.to_string())?;
+    transaction
+        .execute(
+            "UPDATE image_index SET file_name = ?2 WHERE full_path = ?1",
+            params![to_str, to_name],
+        )
+        .map_err(|e| e.to_string())?;
+    transaction
+        .execute(
+            "UPDATE link_index SET source_path = ?2 || substr(source_path, length(?1) + 1)
+             WHERE source_path = ?1 OR substr(source_path, 1, length(?1) + 1) = ?1 || '/'",
+            params![from_str, to_str],
+        )
+        .map_err(|e| e.to_string())?;
+
+    for write in writes {
+        let source = match write.path.strip_prefix(from) {
+            Ok(relative) if !relative.as_os_str().is_empty() => to.join(relative),
+            Ok(_) => to.to_path_buf(),
+            Err(_) => write.path.clone(),
+        };
+        index_note_links(&transaction, &source, &write.updated)?;
+    }
+
+    transaction.commit().map_err(|e| e.to_string())
+}
+
+/// Renames or moves `from` to `to`. Reads and rewrites notes all over the
+/// vault, so it runs off the async runtime.
+fn relocate(
+    app_handle: &tauri::AppHandle,
+    from: PathBuf,
+    to: PathBuf,
+    dry_run: bool,
+) -> Result<RenamePlan, String> {
+    let mut roots: Vec<PathBuf> = load_config(app_handle)?
+        .search_paths
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| path.is_dir())
+        .collect();
+    if let Some(parent) = from.parent() {
+        roots.push(parent.to_path_buf());
+    }
+
+    let (mut plan, writes) = plan_relocation(&from, &to, &roots)?;
+    if dry_run {
+        return Ok(plan);
+    }
+    let state = app_handle
+        .try_state::<DatabaseState>()
+        .ok_or("The index is still opening; try again in a moment")?;
+
+    let history = History::for_app(app_handle);
+    for write in &writes {
+        if let Err(e) = history.record(&write.path, write.original.as_bytes()) {
+            eprintln!(
+                "Failed to record history for {}: {}",
+                write.path.display(),
+                e
+            );
+        }
+    }
+
+    apply_files(&from, &to, &writes)?;
+
+    if let Err(e) = update_indexes(&state.0, &from, &to, &writes) {
+        // Put the files back so they match the unchanged index
+        let _ = move_path(&to, &from);
+        restore_files(&writes.iter().collect::<Vec<_>>());
+        return Err(format!("Failed to update index: {}", e));
+    }
+
+    plan.applied = true;
+    Ok(plan)
+}
+
+/// Renames a note, asset or folder in place and updates every link to it.
+/// With `dry_run`, only reports the links that would change.
+#[tauri::command]
+pub async fn rename_note(
+    app_handle: tauri::AppHandle,
+    path: String,
+    new_name: String,
+    dry_run: Option<bool>,
+) -> Result<RenamePlan, String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name.contains(['/', '\\']) || new_name == ".." {
+        return Err(format!("'{}' is not a valid name", new_name));
+    }
+
+    let from = PathBuf::from(&path);
+    let to = from.with_file_name(new_name);
+    let dry_run = dry_run.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || relocate(&app_handle, from, to, dry_run))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Moves a note, asset or folder into `target_dir` and updates every link to
+/// it, as well as relative links inside it. With `dry_run`, only reports the
+/// links that would change.
+#[tauri::command]
+pub async fn move_note(
+    app_handle: tauri::AppHandle,
+    path: String,
+    target_dir: String,
+    dry_run: Option<bool>,
+) -> Result<RenamePlan, String> {
+    let from = PathBuf::from(&path);
+    let name = from
+        .file_name()
+        .ok_or_else(|| format!("'{}' has no file name", path))?;
+    let to = Path::new(&target_dir).join(name);
+    let dry_run = dry_run.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || relocate(&app_handle, from, to, dry_run))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+
+    fn scratch_vault() -> tempfile::TempDir {
+        let scratch = scratch_dir();
+        let dir = scratch.path();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::create_dir_all(dir.join("archive")).unwrap();
+        fs::create_dir_all(dir.join("img")).unwrap();
+        fs::write(dir.join("img/chart.png"), "png").unwrap();
+        fs::write(
+            dir.join("notes/Plan.md"),
+            "# Plan\n![chart](../img/chart.png)\nSee [today](today.md).\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("notes/today.md"),
+            "Read [[Plan]], [[notes/Plan#Goals|goals]] and [plan](./Plan.md#goals).\n\
+             `[[Plan]]` stays.\n",
+        )
+        .unwrap();
+        scratch
+    }
+
+    #[test]
+    fn test_rename_rewrites_links() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        let from = vault.join("notes/Plan.md");
+        let to = vault.join("notes/Roadmap 2025.md");
+
+        let (plan, writes) = plan_relocation(&from, &to, std::slice::from_ref(&vault)).unwrap();
+        assert_eq!(plan.files.len(), 1);
+        assert_eq!(plan.files[0].edits.len(), 3);
+
+        apply_files(&from, &to, &writes).unwrap();
+        assert!(to.exists() && !from.exists());
+        assert_eq!(
+            fs::read_to_string(vault.join("notes/today.md")).unwrap(),
+            "Read [[Roadmap 2025]], [[notes/Roadmap 2025#Goals|goals]] and [plan](./Roadmap%202025.md#goals).\n\
+             `[[Plan]]` stays.\n"
+        );
+    }
+
+    #[test]
+    fn test_move_keeps_relative_links_of_the_moved_note() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        let from = vault.join("notes/Plan.md");
+        let to = vault.join("archive/Plan.md");
+
+        let (plan, writes) = plan_relocation(&from, &to, std::slice::from_ref(&vault)).unwrap();
+        apply_files(&from, &to, &writes).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&to).unwrap(),
+            "# Plan\n![chart](../img/chart.png)\nSee [today](../notes/today.md).\n"
+        );
+        let today = fs::read_to_string(vault.join("notes/today.md")).unwrap();
+        assert!(today.contains("[[Plan]]"));
+        assert!(today.contains("[[archive/Plan#Goals|goals]]"));
+        assert!(today.contains("[plan](../archive/Plan.md#goals)"));
+        assert_eq!(plan.files.len(), 2);
+    }
+
+    #[test]
+    fn test_rename_leaves_links_to_a_same_named_note() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        fs::create_dir_all(vault.join("archive")).unwrap();
+        fs::write(vault.join("archive/Plan.md"), "# Old plan\n").unwrap();
+        fs::write(vault.join("archive/log.md"), "See [[Plan]].\n").unwrap();
+        let from = vault.join("archive/Plan.md");
+        let to = vault.join("archive/Old plan.md");
+
+        let (plan, writes) = plan_relocation(&from, &to, std::slice::from_ref(&vault)).unwrap();
+        apply_files(&from, &to, &writes).unwrap();
+
+        assert_eq!(plan.files.len(), 1);
+        assert_eq!(
+            fs::read_to_string(vault.join("archive/log.md")).unwrap(),
+            "See [[Old plan]].\n"
+        );
+        let today = fs::read_to_string(vault.join("notes/today.md")).unwrap();
+        assert!(today.starts_with("Read [[Plan]], [[notes/Plan#Goals|goals]]"));
+    }
+
+    #[test]
+    fn test_rename_follows_vault_link_settings() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        let settings = |format: &str| {
+            format!(
+                "{{\"newLinkFormat\": \"{}\", \"useMarkdownLinks\": true}}",
+                format
+            )
+        };
+        fs::write(vault.join(".obsidian/app.json"), settings("relative")).unwrap();
+        fs::write(
+            vault.join("archive/log.md"),
+            "See [[../notes/Plan|plan]].\n",
+        )
+        .unwrap();
+        let from = vault.join("notes/Plan.md");
+        let to = vault.join("notes/Roadmap.md");
+
+        let (_, writes) = plan_relocation(&from, &to, std::slice::from_ref(&vault)).unwrap();
+        apply_files(&from, &to, &writes).unwrap();
+        assert_eq!(
+            fs::read_to_string(vault.join("archive/log.md")).unwrap(),
+            "See [[../notes/Roadmap|plan]].\n"
+        );
+
+        // Markdown links from the vault root do not change when the note moves
+        fs::write(vault.join(".obsidian/app.json"), settings("absolute")).unwrap();
+        fs::write(
+            vault.join("archive/log.md"),
+            "See [plan](notes/Roadmap.md) and [chart](img/chart.png).\n",
+        )
+        .unwrap();
+        let (from, to) = (vault.join("archive/log.md"), vault.join("notes/log.md"));
+
+        let (plan, writes) = plan_relocation(&from, &to, std::slice::from_ref(&vault)).unwrap();
+        assert!(plan.files.is_empty());
+        apply_files(&from, &to, &writes).unwrap();
+
+        let (from, to) = (
+            vault.join("notes/Roadmap.md"),
+            vault.join("archive/Roadmap.md"),
+        );
+        let (_, writes) = plan_relocation(&from, &to, std::slice::from_ref(&vault)).unwrap();
+        apply_files(&from, &to, &writes).unwrap();
+        assert_eq!(
+            fs::read_to_string(vault.join("notes/log.md")).unwrap(),
+            "See [plan](archive/Roadmap.md) and [chart](img/chart.png).\n"
+        );
+    }
+
+    #[test]
+    fn test_failed_move_restores_notes() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        let from = vault.join("notes/Plan.md");
+        let to = vault.join("archive/Plan.md");
+        let today_before = fs::read_to_string(vault.join("notes/today.md")).unwrap();
+
+        let (_, writes) = plan_relocation(&from, &to, std::slice::from_ref(&vault)).unwrap();
+        // The destination disappears between planning and applying
+        fs::remove_dir_all(vault.join("archive")).unwrap();
+
+        assert!(apply_files(&from, &to, &writes).is_err());
+        assert!(from.exists());
+        assert_eq!(
+            fs::read_to_string(vault.join("notes/today.md")).unwrap(),
+            today_before
+        );
+    }
+
+    #[test]
+    fn test_rejects_existing_destination() {
+        let scratch = scratch_vault();
+        let vault = scratch.path().to_path_buf();
+        let from = vault.join("notes/Plan.md");
+        let to = vault.join("notes/today.md");
+        assert!(plan_relocation(&from, &to, &[vault]).is_err());
+    }
+}
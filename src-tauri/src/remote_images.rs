@@ -0,0 +1,305 @@
+use crate::config::Config;
+use crate::file_io::atomic_write;
+use crate::helper::get_data_path;
+use crate::image_cache::DatabaseState;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+
+/// Folder in the app data dir holding downloaded images, each named after
+/// the SHA-256 of its content.
+pub const REMOTE_CACHE_DIR: &str = "remote-images";
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+// URLs being downloaded, so re-rendering a note does not fetch them twice
+static IN_FLIGHT: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+pub fn is_remote(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// File extension for an image `Content-Type`; anything else is not cached.
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_lowercase();
+    let extension = match mime.as_str() {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/bmp" => "bmp",
+        _ => return None,
+    };
+    Some(extension)
+}
+
+fn now_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+/// Downloaded copies of remote images. `remote_images` maps each URL to its
+/// file; URLs serving the same bytes share one.
+#[derive(Debug, Clone)]
+pub struct RemoteImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl RemoteImageCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        RemoteImageCache { dir, max_bytes }
+    }
+
+    /// The cache `config` asks for, or `None` while `remote_images` is off.
+    pub fn from_config(app_handle: &tauri::AppHandle, config: &Config) -> Option<Self> {
+        let settings = &config.remote_images;
+        settings.enabled.then(|| {
+            Self::new(
+                get_data_path(app_handle, REMOTE_CACHE_DIR),
+                settings.max_cache_mb * 1024 * 1024,
+            )
+        })
+    }
+
+    /// The local copy of `url`, if it was downloaded, marking it as used.
+    pub fn cached(&self, connection: &Connection, url: &str) -> Option<PathBuf> {
+        let file_name: String = connection
+            .query_row(
+                "SELECT file_name FROM remote_images WHERE url = ?1",
+                params![url],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()??;
+        let path = self.dir.join(&file_name);
+        if !path.is_file() {
+            return None;
+        }
+
+        let _ = connection.execute(
+            "UPDATE remote_images SET last_used = ?1 WHERE url = ?2",
+            params![now_nanos(), url],
+        );
+        Some(path)
+    }
+
+    /// Downloads `url` into the cache and returns the file name and size.
+    /// Only images no larger than the whole cache are kept.
+    pub fn download(&self, url: &str) -> Result<(String, u64), String> {
+        let agent = ureq::Agent::new_with_config(
+            ureq::Agent::config_builder()
+                .timeout_global(Some(DOWNLOAD_TIMEOUT))
+                .build(),
+        );
+        let mut response = agent.get(url).call().map_err(|e| e.to_string())?;
+
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let extension = extension_for(content_type)
+            .ok_or_else(|| format!("'{}' is not an image ({})", url, content_type))?;
+        let bytes = response
+            .body_mut()
+            .with_config()
+            .limit(self.max_bytes)
+            .read_to_vec()
+            .map_err(|e| e.to_string())?;
+
+        let hash: String = Sha256::digest(&bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let file_name = format!("{}.{}", hash, extension);
+        let path = self.dir.join(&file_name);
+        if !path.is_file() {
+            fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+            atomic_write(&path, &bytes).map_err(|e| e.to_string())?;
+        }
+
+        Ok((file_name, bytes.len() as u64))
+    }
+
+    /// Points `url` at a downloaded file, then evicts the least recently used
+    /// files until the cache fits in its size limit.
+    pub fn record(
+        &self,
+        connection: &Connection,
+        url: &str,
+        file_name: &str,
+        size: u64,
+    ) -> Result<(), String> {
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO remote_images (url, file_name, size, last_used)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![url, file_name, size as i64, now_nanos()],
+            )
+            .map_err(|e| e.to_string())?;
+        self.evict(connection)
+    }
+
+    fn evict(&self, connection: &Connection) -> Result<(), String> {
+        let mut statement = connection
+            .prepare(
+                "SELECT file_name, MAX(size), MAX(last_used) AS used FROM remote_images
+                 GROUP BY file_name ORDER BY used",
+            )
+            .map_err(|e| e.to_string())?;
+        let files: Vec<(String, i64)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .filter_map(|row| row.ok())
+            .collect();
+
+        let mut total: u64 = files.iter().map(|(_, size)| *size as u64).sum();
+        for (file_name, size) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            connection
+                .execute(
+                    "DELETE FROM remote_images WHERE file_name = ?1",
+                    params![file_name],
+                )
+                .map_err(|e| e.to_string())?;
+            if let Err(e) = fs::remove_file(self.dir.join(&file_name)) {
+                eprintln!("Failed to evict cached image {}: {}", file_name, e);
+            }
+            total = total.saturating_sub(size as u64);
+        }
+        Ok(())
+    }
+}
+
+/// Downloads `urls` on a background thread, so the next render of the note
+/// shows them from the cache. URLs already being fetched are skipped.
+pub fn fetch_in_background(
+    app_handle: tauri::AppHandle,
+    cache: RemoteImageCache,
+    urls: Vec<String>,
+) {
+    let urls: Vec<String> = {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        urls.into_iter()
+            .filter(|url| in_flight.insert(url.clone()))
+            .collect()
+    };
+    if urls.is_empty() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        for url in urls {
+            let result = cache.download(&url).and_then(|(file_name, size)| {
+                let state = app_handle
+                    .try_state::<DatabaseState>()
+                    .ok_or("Image index is not ready")?;
+                let connection = state.0.lock().unwrap();
+                cache.record(&connection, &url, &file_name, size)
+            });
+            if let Err(e) = result {
+                eprintln!("Failed to cache {}: {}", url, e);
+            }
+            IN_FLIGHT.lock().unwrap().remove(&url);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+    use crate::image_cache::ensure_schema_exists;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Answers each request with the `(path, content type, body)` it names.
+    fn serve(routes: Vec<(&'static str, &'static str, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let mut stream = reader.into_inner();
+                match routes.iter().find(|(route, _, _)| *route == path) {
+                    Some((_, content_type, body)) => {
+                        let head = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            content_type,
+                            body.len()
+                        );
+                        stream.write_all(head.as_bytes()).unwrap();
+                        stream.write_all(body).unwrap();
+                    }
+                    None => stream
+                        .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                        .unwrap(),
+                }
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn test_remote_image_cache() {
+        let server = serve(vec![
+            ("/a.png", "image/png", vec![1; 400]),
+            ("/mirror/a.png", "image/png", vec![1; 400]),
+            ("/b.jpg", "image/jpeg; charset=binary", vec![2; 400]),
+            ("/c.gif", "image/gif", vec![3; 400]),
+            ("/page", "text/html", b"<html></html>".to_vec()),
+            ("/huge.png", "image/png", vec![4; 2000]),
+        ]);
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let connection = Connection::open_in_memory().unwrap();
+        ensure_schema_exists(&connection);
+        let cache = RemoteImageCache::new(dir.clone(), 1000);
+
+        let fetch = |path: &str| {
+            let url = format!("{}{}", server, path);
+            let (file_name, size) = cache.download(&url)?;
+            cache.record(&connection, &url, &file_name, size)?;
+            Ok::<String, String>(url)
+        };
+
+        let a = fetch("/a.png").unwrap();
+        let mirror = fetch("/mirror/a.png").unwrap();
+        let cached = cache.cached(&connection, &a).unwrap();
+        assert_eq!(cached, cache.cached(&connection, &mirror).unwrap());
+        assert_eq!(fs::read(&cached).unwrap(), vec![1; 400]);
+        assert!(cached.to_string_lossy().ends_with(".png"));
+
+        assert!(fetch("/page").is_err());
+        assert!(fetch("/huge.png").is_err());
+        assert!(fetch("/missing.png").is_err());
+
+        // b is the least recently used once a is shown again
+        let b = fetch("/b.jpg").unwrap();
+        cache.cached(&connection, &a).unwrap();
+        let c = fetch("/c.gif").unwrap();
+        assert!(cache.cached(&connection, &b).is_none());
+        assert!(cache.cached(&connection, &a).is_some());
+        assert!(cache.cached(&connection, &c).is_some());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+}
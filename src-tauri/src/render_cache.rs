@@ -0,0 +1,218 @@
+use crate::config::Config;
+use crate::file_io::atomic_write;
+use crate::markdown_parse::{Heading, RenderChunk};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Bumped whenever the renderer's output changes, so older cached HTML is
+/// not served by a newer build.
+const RENDERER_VERSION: u32 = 5;
+
+/// Renders kept in memory, for flipping between the notes open in tabs.
+const MEMORY_ENTRIES: usize = 16;
+
+/// Renders kept on disk; the least recently used beyond this are removed.
+const DISK_ENTRIES: usize = 256;
+
+/// Everything besides the note itself that a render depends on.
+#[derive(Serialize, Debug)]
+pub struct RenderOptions<'a> {
+    pub current_file_path: Option<&'a str>,
+    pub pixel_ratio: u32,
+    /// Line the content starts on, when it is the end of a note.
+    pub first_line: usize,
+    pub chunk_bytes: usize,
+    pub config: &'a Config,
+    /// `index_version` of the image index the images were looked up in.
+    pub index_version: Option<(i64, i64, i64)>,
+}
+
+impl RenderOptions<'_> {
+    /// Cache key for rendering `content` with these options.
+    pub fn key(&self, content: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(RENDERER_VERSION.to_le_bytes());
+        hasher.update(serde_json::to_vec(self).unwrap_or_default());
+        hasher.update([0]);
+        hasher.update(content.as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CachedRender {
+    pub chunks: Vec<RenderChunk>,
+    pub outline: Vec<Heading>,
+    /// Local files the HTML loads; they are let into the asset scope again
+    /// on a hit, and the entry is dropped once one of them is gone.
+    pub files: Vec<PathBuf>,
+    /// Where the images that did not resolve would be next to the note; the
+    /// entry is dropped once one of them is created.
+    pub missing: Vec<PathBuf>,
+}
+
+/// Rendered notes, in memory and in `dir`, by `RenderOptions::key`.
+#[derive(Debug)]
+pub struct RenderCache {
+    dir: PathBuf,
+    /// Most recently used first.
+    memory: Mutex<VecDeque<(String, CachedRender)>>,
+}
+
+impl RenderCache {
+    pub fn new(dir: PathBuf) -> Self {
+        RenderCache {
+            dir,
+            memory: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedRender> {
+        let mut memory = self.memory.lock().unwrap();
+        let render = match memory.iter().position(|(cached, _)| cached == key) {
+            Some(index) => memory.remove(index).map(|(_, render)| render)?,
+            None => {
+                let path = self.dir.join(format!("{}.json", key));
+                let render: CachedRender = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+                // The modification time orders entries for pruning
+                let _ = File::options()
+                    .append(true)
+                    .open(&path)
+                    .and_then(|file| file.set_modified(SystemTime::now()));
+                render
+            }
+        };
+        if !render.files.iter().all(|file| file.is_file())
+            || render.missing.iter().any(|file| file.is_file())
+        {
+            let _ = fs::remove_file(self.dir.join(format!("{}.json", key)));
+            return None;
+        }
+
+        memory.push_front((key.to_string(), render.clone()));
+        memory.truncate(MEMORY_ENTRIES);
+        Some(render)
+    }
+
+    pub fn put(&self, key: &str, render: CachedRender) {
+        let result = serde_json::to_vec(&render)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| {
+                fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+                atomic_write(&self.dir.join(format!("{}.json", key)), &bytes)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            eprintln!("Failed to cache render: {}", e);
+        }
+        self.prune();
+
+        let mut memory = self.memory.lock().unwrap();
+        memory.retain(|(cached, _)| cached != key);
+        memory.push_front((key.to_string(), render));
+        memory.truncate(MEMORY_ENTRIES);
+    }
+
+    /// Removes the least recently used renders beyond `DISK_ENTRIES`.
+    fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut files: Vec<(SystemTime, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+                Some((modified, entry.path()))
+            })
+            .collect();
+        if files.len() <= DISK_ENTRIES {
+            return;
+        }
+
+        files.sort();
+        for (_, path) in &files[..files.len() - DISK_ENTRIES] {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+
+    #[test]
+    fn test_render_cache() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let image = dir.join("a.png");
+        fs::write(&image, "x").unwrap();
+
+        let config = Config::default();
+        let options = RenderOptions {
+            current_file_path: Some("/notes/a.md"),
+            pixel_ratio: 1,
+            first_line: 1,
+            chunk_bytes: usize::MAX,
+            config: &config,
+            index_version: Some((1, 1, 10)),
+        };
+        let key = options.key("# A");
+        assert_ne!(key, options.key("# B"));
+        assert_ne!(
+            key,
+            RenderOptions {
+                index_version: Some((2, 2, 20)),
+                ..options
+            }
+            .key("# A")
+        );
+
+        let render = CachedRender {
+            chunks: Vec::new(),
+            outline: Vec::new(),
+            files: vec![image.clone()],
+            missing: vec![dir.join("b.png")],
+        };
+        let cache = RenderCache::new(dir.join("cache"));
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, render.clone());
+        assert_eq!(cache.get(&key), Some(render.clone()));
+
+        // A fresh cache reads the entry back from disk
+        let reopened = RenderCache::new(dir.join("cache"));
+        assert_eq!(reopened.get(&key), Some(render));
+
+        fs::remove_file(&image).unwrap();
+        assert!(RenderCache::new(dir.join("cache")).get(&key).is_none());
+        assert_eq!(fs::read_dir(dir.join("cache")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_render_cache_drops_entries_once_missing_images_appear() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let render = CachedRender {
+            chunks: Vec::new(),
+            outline: Vec::new(),
+            files: Vec::new(),
+            missing: vec![dir.join("b.png")],
+        };
+        let cache = RenderCache::new(dir.join("cache"));
+        cache.put("key", render.clone());
+        assert_eq!(cache.get("key"), Some(render));
+
+        fs::write(dir.join("b.png"), "x").unwrap();
+        assert!(cache.get("key").is_none());
+        assert!(RenderCache::new(dir.join("cache")).get("key").is_none());
+    }
+}
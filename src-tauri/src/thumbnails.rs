@@ -0,0 +1,323 @@
+use crate::asset_protocol::{error_response, is_allowed, request_path};
+use crate::file_io::atomic_write;
+use crate::helper::cached_config;
+use crate::image_cache::media_mime_type;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{ImageFormat, ImageReader};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::Manager;
+
+/// Served as `thumb://localhost/<encoded path>?dpr=<pixel ratio>`.
+pub const THUMBNAIL_SCHEME: &str = "thumb";
+
+/// Formats worth downsizing; GIFs would lose their animation and SVGs scale anyway.
+const THUMBNAIL_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "webp", "bmp"];
+
+/// Smaller files are served as they are; decoding them costs more than it saves.
+const MIN_THUMBNAIL_BYTES: u64 = 512 * 1024;
+
+/// Used when `max_width` is relative to the window (`%`, `vw`, ...).
+const FALLBACK_DISPLAY_WIDTH: u32 = 1200;
+
+/// Thumbnails kept on disk; the least recently used beyond this are removed.
+const MAX_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Bytes in the thumbnail cache, counted on the first thumbnail written and
+/// again whenever it is pruned.
+static CACHE_BYTES: Mutex<Option<u64>> = Mutex::new(None);
+
+const MAX_PIXEL_RATIO: u32 = 3;
+const JPEG_QUALITY: u8 = 85;
+
+/// CSS pixels the reading column is wide for a `max_width` such as `800px` or
+/// `70ch`. Font-relative units assume the default 16px font.
+pub fn display_width(max_width: &str) -> u32 {
+    let value = max_width.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let Ok(number) = number.parse::<f64>() else {
+        return FALLBACK_DISPLAY_WIDTH;
+    };
+
+    let px = match unit {
+        "px" => number,
+        "em" | "rem" => number * 16.0,
+        "ch" | "ex" => number * 8.0,
+        "pt" => number * 4.0 / 3.0,
+        "pc" => number * 16.0,
+        "in" => number * 96.0,
+        "cm" => number * 96.0 / 2.54,
+        "mm" => number * 96.0 / 25.4,
+        _ => return FALLBACK_DISPLAY_WIDTH,
+    };
+    (px.round() as u32).max(1)
+}
+
+pub(crate) fn is_thumbnailable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| THUMBNAIL_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Changes whenever the file is replaced or the requested width changes.
+fn cache_key(path: &Path, metadata: &fs::Metadata, width: u32) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+
+    let mut hasher = Sha256::new();
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update(format!("\0{}\0{}\0{}", modified, metadata.len(), width));
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The bytes to display for the image at `path`, at most `width` pixels wide,
+/// and their MIME type. Large images are downsized once and kept in
+/// `cache_dir`; small, narrow or vector images are returned unchanged.
+pub fn thumbnail_for(
+    cache_dir: &Path,
+    path: &Path,
+    width: u32,
+) -> Result<(Vec<u8>, &'static str), String> {
+    let mime = media_mime_type(path)
+        .filter(|mime| mime.starts_with("image/"))
+        .ok_or_else(|| format!("'{}' is not an image", path.display()))?;
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    let original = || {
+        fs::read(path)
+            .map(|bytes| (bytes, mime))
+            .map_err(|e| e.to_string())
+    };
+
+    if !is_thumbnailable(path) || metadata.len() < MIN_THUMBNAIL_BYTES {
+        return original();
+    }
+
+    let key = cache_key(path, &metadata, width);
+    for (extension, cached_mime) in [("jpg", "image/jpeg"), ("png", "image/png")] {
+        let cached = cache_dir.join(format!("{}.{}", key, extension));
+        if let Ok(bytes) = fs::read(&cached) {
+            // The modification time orders thumbnails for pruning
+            let _ = File::options()
+                .append(true)
+                .open(&cached)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            return Ok((bytes, cached_mime));
+        }
+    }
+
+    let reader = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?;
+    let (original_width, _) = reader.into_dimensions().map_err(|e| e.to_string())?;
+    if original_width <= width {
+        return original();
+    }
+
+    let image = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?
+        .decode()
+        .map_err(|e| e.to_string())?;
+    let thumbnail = image.resize(width, u32::MAX, FilterType::Triangle);
+
+    // JPEG is far smaller for screenshots and photos; PNG keeps transparency
+    let mut bytes = Vec::new();
+    let (extension, thumbnail_mime) = if thumbnail.color().has_alpha() {
+        thumbnail
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .map_err(|e| e.to_string())?;
+        ("png", "image/png")
+    } else {
+        JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)
+            .encode_image(&thumbnail.to_rgb8())
+            .map_err(|e| e.to_string())?;
+        ("jpg", "image/jpeg")
+    };
+
+    match fs::create_dir_all(cache_dir)
+        .and_then(|_| atomic_write(&cache_dir.join(format!("{}.{}", key, extension)), &bytes))
+    {
+        Ok(()) => count_cached(cache_dir, bytes.len() as u64),
+        Err(e) => eprintln!("Failed to cache thumbnail of {}: {}", path.display(), e),
+    }
+
+    Ok((bytes, thumbnail_mime))
+}
+
+/// Adds a thumbnail just written to the cache size, and prunes the cache
+/// only once it grows past `MAX_CACHE_BYTES`.
+fn count_cached(cache_dir: &Path, bytes: u64) {
+    let mut cache_bytes = CACHE_BYTES.lock().unwrap();
+    let total = match *cache_bytes {
+        Some(total) if total + bytes <= MAX_CACHE_BYTES => total + bytes,
+        _ => prune_cache(cache_dir, MAX_CACHE_BYTES),
+    };
+    *cache_bytes = Some(total);
+}
+
+/// Removes the least recently used thumbnails until the rest fit in
+/// `max_bytes`, and returns the size of the rest.
+fn prune_cache(cache_dir: &Path, max_bytes: u64) -> u64 {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return 0;
+    };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort();
+    for (_, size, path) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Failed to remove thumbnail {}: {}", path.display(), e);
+        }
+        total = total.saturating_sub(size);
+    }
+    total
+}
+
+/// The device pixel ratio a thumbnail request asks for with `?dpr=`.
+fn pixel_ratio(request: &Request<Vec<u8>>) -> u32 {
+    request
+        .uri()
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("dpr="))
+        .and_then(|value| value.parse::<f64>().ok())
+        .map(|ratio| (ratio.ceil() as u32).clamp(1, MAX_PIXEL_RATIO))
+        .unwrap_or(1)
+}
+
+/// Answers a `thumb://` request with an image sized for the reading column,
+/// for the same files `readtext://` serves.
+pub fn handle_request(
+    app_handle: &tauri::AppHandle,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let Some(path) = request_path(request) else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "Invalid thumbnail path".to_string(),
+        );
+    };
+    // Loaded at startup and kept current by the config watcher; never read
+    // from disk per image
+    let config = cached_config().unwrap_or_default();
+    if !is_allowed(&path, &config.search_paths) {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            format!("'{}' is outside the allowed folders", path.display()),
+        );
+    }
+    let width = display_width(&config.layout.max_width) * pixel_ratio(request);
+
+    let cache_dir = match app_handle.path().app_cache_dir() {
+        Ok(dir) => dir.join("thumbnails"),
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+
+    match thumbnail_for(&cache_dir, &path, width) {
+        Ok((bytes, mime)) => Response::builder()
+            .header(header::CONTENT_TYPE, mime)
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(bytes)
+            .unwrap_or_default(),
+        Err(e) => error_response(StatusCode::NOT_FOUND, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::scratch_dir;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("800px"), 800);
+        assert_eq!(display_width("70ch"), 560);
+        assert_eq!(display_width("50rem"), 800);
+        assert_eq!(display_width("100%"), FALLBACK_DISPLAY_WIDTH);
+        assert_eq!(display_width("none"), FALLBACK_DISPLAY_WIDTH);
+    }
+
+    #[test]
+    fn test_thumbnail_is_downsized_and_cached() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        let cache_dir = dir.join("cache");
+
+        // Noise barely compresses, so the PNG is well above MIN_THUMBNAIL_BYTES
+        let mut seed: u32 = 7;
+        let large = RgbImage::from_fn(1600, 400, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let [r, g, b, _] = seed.to_le_bytes();
+            Rgb([r, g, b])
+        });
+        let large_path = dir.join("screenshot.png");
+        large.save(&large_path).unwrap();
+
+        let (bytes, mime) = thumbnail_for(&cache_dir, &large_path, 400).unwrap();
+        assert_eq!(mime, "image/jpeg");
+        let thumbnail = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (400, 100));
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+        assert_eq!(
+            thumbnail_for(&cache_dir, &large_path, 400).unwrap().0,
+            bytes
+        );
+
+        let small_path = dir.join("icon.png");
+        RgbImage::new(32, 32).save(&small_path).unwrap();
+        let (bytes, mime) = thumbnail_for(&cache_dir, &small_path, 400).unwrap();
+        assert_eq!((bytes, mime), (fs::read(&small_path).unwrap(), "image/png"));
+    }
+
+    #[test]
+    fn test_prune_removes_least_recently_used() {
+        let scratch = scratch_dir();
+        let dir = scratch.path().to_path_buf();
+        for (age, name) in [(30, "old.jpg"), (20, "used.jpg"), (10, "new.png")] {
+            let path = dir.join(name);
+            fs::write(&path, [0; 100]).unwrap();
+            let modified = SystemTime::now() - std::time::Duration::from_secs(age);
+            File::options()
+                .append(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(modified))
+                .unwrap();
+        }
+
+        assert_eq!(prune_cache(&dir, 300), 300);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        assert_eq!(prune_cache(&dir, 250), 200);
+        assert!(!dir.join("old.jpg").exists());
+        assert!(dir.join("used.jpg").exists() && dir.join("new.png").exists());
+    }
+}
@@ -50,6 +50,14 @@ export default function App() {
     }
   };
 
+  // Follow the open file as it grows, or stop, from the next watch on
+  const handleFollowChange = async (follow: boolean) => {
+    const path = fileSystem.currentPath;
+    if (!path) return;
+    fileSystem.setFollow(path, follow);
+    await fileSystem.startWatch(path);
+  };
+
   const handleFileOpen = async (path: string) => {
     if (tabs.instanceMode) {
       const existingTab = tabs.getTabByPath(path);
@@ -91,7 +99,14 @@ export default function App() {
       />
       <MainWindow 
         content={fileSystem.content} 
+        lastUpdate={fileSystem.lastUpdate}
+        stamp={fileSystem.stamp}
         currentPath={fileSystem.currentPath} 
+        follow={
+          !!fileSystem.currentPath &&
+          fileSystem.followedPaths.has(fileSystem.currentPath)
+        }
+        onFollowChange={handleFollowChange}
         rootPath={fileSystem.currentFolder}
         defaultActivePanel={defaultActivePanel}
         onFileOpen={handleFileOpen}
@@ -50,6 +50,14 @@ export const SaveIcon = memo(() => (
   </svg>
 ));
 
+export const FollowIcon = memo(() => (
+  <svg width="24" height="24" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round">
+    <line x1="12" y1="3" x2="12" y2="15"></line>
+    <polyline points="6 9 12 15 18 9"></polyline>
+    <line x1="5" y1="20" x2="19" y2="20"></line>
+  </svg>
+));
+
 // Explorer Icons
 export const ChevronRightIcon = memo(() => (
   <svg width="16" height="16" viewBox="0 0 16 16" fill="currentColor">
@@ -29,6 +29,9 @@ import "../styles/editor.css";
 export interface MarkdownEditorHandle {
   scrollToPercent: (percent: number) => void;
   getScrollPercent: () => number;
+  /** Line at the top of the editor, with the fraction of it scrolled past. */
+  getTopLine: () => number;
+  scrollToLine: (line: number) => void;
 }
 
 interface MarkdownEditorProps {
@@ -114,6 +117,12 @@ const lightTheme = EditorView.theme({
   },
 });
 
+// Where the document starts inside the scroller, below the content padding
+const documentOffset = (view: EditorView): number =>
+  view.documentTop -
+  view.scrollDOM.getBoundingClientRect().top +
+  view.scrollDOM.scrollTop;
+
 export const MarkdownEditor = memo(
   forwardRef<MarkdownEditorHandle, MarkdownEditorProps>(
     (
@@ -142,6 +151,27 @@ export const MarkdownEditor = memo(
             viewRef.current.scrollDOM;
           return (scrollTop / (scrollHeight - clientHeight)) * 100;
         },
+        getTopLine: () => {
+          const view = viewRef.current;
+          if (!view) return 1;
+          const height = view.scrollDOM.scrollTop - documentOffset(view);
+          const block = view.lineBlockAtHeight(height);
+          const fraction = (height - block.top) / (block.height || 1);
+          return (
+            view.state.doc.lineAt(block.from).number +
+            Math.min(Math.max(fraction, 0), 1)
+          );
+        },
+        scrollToLine: (line: number) => {
+          const view = viewRef.current;
+          if (!view) return;
+          const doc = view.state.doc;
+          const number = Math.min(Math.max(Math.floor(line), 1), doc.lines);
+          const block = view.lineBlockAt(doc.line(number).from);
+          view.scrollDOM.scrollTo({
+            top: documentOffset(view) + block.top + block.height * (line - number),
+          });
+        },
       }));
 
       // Handle save shortcut
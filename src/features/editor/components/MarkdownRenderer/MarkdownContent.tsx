@@ -1,6 +1,6 @@
-import { useEffect, useState, memo, RefObject } from "react";
-import { escapeRegExp } from "../../utils/regex";
+import { useEffect, memo, RefObject } from "react";
 import { applyHeadingIds, highlightCodeBlocks } from "../../utils/domHelpers";
+import { RenderChunk } from "../../../../types";
 
 // Language Imports
 import "prism-themes/themes/prism-vs.css";
@@ -23,54 +23,49 @@ import "prismjs/components/prism-toml";
 import "prismjs/components/prism-shell-session";
 import "prismjs/components/prism-docker";
 import "prismjs/components/prism-sql";
-import { useImageContext } from "../../../../context/ImageContext";
 
 interface MarkdownContentProps {
-  htmlContent: string;
+  chunks: RenderChunk[];
   containerRef: RefObject<HTMLDivElement | null>;
 }
 
 export const MarkdownContent = memo(
-  ({ htmlContent, containerRef }: MarkdownContentProps) => {
-    const { resolvedPaths, transformUrl } = useImageContext();
-    const [processedHtml, setProcessedHtml] = useState(htmlContent);
-
+  ({ chunks, containerRef }: MarkdownContentProps) => {
     useEffect(() => {
-      if (!htmlContent) return;
-      if (resolvedPaths.size === 0) {
-        setProcessedHtml(htmlContent);
-        return;
-      }
+      const container = containerRef.current;
+      if (!container) return;
 
-      let updatedHtml = htmlContent;
-      resolvedPaths.forEach((resolvedPath, originalSrc) => {
-        if (resolvedPath) {
-          const assetUrl = transformUrl(resolvedPath);
-          const srcPattern = new RegExp(
-            `src=["']${escapeRegExp(originalSrc)}["']`,
-            "g",
-          );
-          updatedHtml = updatedHtml.replace(srcPattern, `src="${assetUrl}"`);
-        }
-      });
+      // Thumbnails carry their original in data-full-src; a click loads it
+      const loadFullImage = (event: MouseEvent) => {
+        const target = event.target;
+        if (!(target instanceof HTMLImageElement) || !target.dataset.fullSrc) return;
+        target.src = target.dataset.fullSrc;
+        delete target.dataset.fullSrc;
+      };
 
-      setProcessedHtml(updatedHtml);
-    }, [htmlContent, resolvedPaths, transformUrl]);
+      container.addEventListener("click", loadFullImage);
+      return () => container.removeEventListener("click", loadFullImage);
+    }, [containerRef]);
 
     useEffect(() => {
       const container = containerRef.current;
-      if (!container || !processedHtml) return;
+      if (!container || chunks.length === 0) return;
 
       applyHeadingIds(container);
       highlightCodeBlocks(container);
-    }, [processedHtml, containerRef]);
+    }, [chunks, containerRef]);
 
+    // Chunks that did not change keep their DOM when later ones arrive
     return (
-      <div
-        ref={containerRef}
-        className="prose-wrapper"
-        dangerouslySetInnerHTML={{ __html: processedHtml }}
-      />
+      <div ref={containerRef} className="prose-wrapper">
+        {chunks.map((chunk) => (
+          <div
+            key={chunk.index}
+            className="render-chunk"
+            dangerouslySetInnerHTML={{ __html: chunk.html }}
+          />
+        ))}
+      </div>
     );
   },
 );
@@ -2,7 +2,7 @@ import { useRef, memo } from "react";
 import { MarkdownRendererProps } from "../../types";
 import { useMarkdownParser } from "../../hooks/useMarkdownParser";
 import { useMouseFontSize } from "../../hooks/useMouseFontSize";
-import { ImageProvider } from "../../../../context/ImageContext";
+import { useVaultSnippets } from "../../hooks/useVaultSnippets";
 import { MarkdownContent } from "./MarkdownContent";
 
 const FONT_SIZE_CONFIG = {
@@ -14,19 +14,15 @@ const FONT_SIZE_CONFIG = {
 };
 
 export const MarkdownRenderer = memo(
-  ({ content, currentPath }: MarkdownRendererProps) => {
+  ({ content, currentPath, follow, onRendered }: MarkdownRendererProps) => {
     const containerRef = useRef<HTMLDivElement>(null);
-    const htmlContent = useMarkdownParser(content);
+    const chunks = useMarkdownParser(content, currentPath, follow, onRendered);
 
     useMouseFontSize(FONT_SIZE_CONFIG);
+    useVaultSnippets(currentPath);
 
     return (
-      <ImageProvider htmlContent={htmlContent} currentPath={currentPath}>
-        <MarkdownContent
-          htmlContent={htmlContent}
-          containerRef={containerRef}
-        />
-      </ImageProvider>
+      <MarkdownContent chunks={chunks} containerRef={containerRef} />
     );
   },
 );
@@ -1,14 +1,120 @@
-import { useState, useEffect } from "react";
-import { invoke } from "@tauri-apps/api/core";
+import { useState, useEffect, useRef } from "react";
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { TAURI_COMMANDS } from "../../../utils/constants";
+import { HeadingData, RenderChunk, RenderedNote } from "../../../types";
 
-export const useMarkdownParser = (markdown: string) => {
-  const [html, setHtml] = useState("");
+/** The last render that finished, which a followed note is appended to. */
+interface Rendered {
+  markdown: string;
+  currentPath: string | null;
+  chunks: RenderChunk[];
+  outline: HeadingData[];
+  /** Line the last chunk, holding only the last block, starts on. */
+  lastLine: number;
+  /** Where `lastLine` starts in `markdown`. */
+  lastOffset: number;
+}
+
+/** Offset of `line` in `text`, counting on from `offset`, where `fromLine` starts. */
+const offsetOfLine = (text: string, line: number, offset: number, fromLine: number) => {
+  for (let current = fromLine; current < line; current++) {
+    const next = text.indexOf("\n", offset);
+    if (next === -1) break;
+    offset = next + 1;
+  }
+  return offset;
+};
+
+/**
+ * Renders markdown with its images already resolved to loadable URLs. Long
+ * notes arrive in chunks of whole blocks, each shown as soon as it is ready.
+ * While `follow` is on, text appended to the note re-renders only its last
+ * block onwards, as earlier blocks cannot change. `onRendered` gets the
+ * note's outline and the markdown rendered once the whole note is shown.
+ */
+export const useMarkdownParser = (
+  markdown: string,
+  currentPath: string | null,
+  follow = false,
+  onRendered?: (outline: HeadingData[], markdown: string) => void,
+) => {
+  const [chunks, setChunks] = useState<RenderChunk[]>([]);
+  const renderedRef = useRef<Rendered | null>(null);
+  const onRenderedRef = useRef(onRendered);
+  onRenderedRef.current = onRendered;
 
   useEffect(() => {
-    invoke<string>("parse_markdown_to_html", { content: markdown })
-      .then(setHtml)
+    const rendered = renderedRef.current;
+    const samePath = rendered?.currentPath === currentPath;
+    // Only `follow` changed
+    if (samePath && rendered?.markdown === markdown) return;
+
+    let kept: RenderChunk[] = [];
+    let content = markdown;
+    let firstLine = 1;
+    let firstOffset = 0;
+    // A followed note grows by appends, so only its last block is compared
+    if (
+      follow &&
+      samePath &&
+      rendered &&
+      rendered.lastLine > 1 &&
+      markdown.length > rendered.markdown.length &&
+      markdown.startsWith(rendered.markdown.slice(rendered.lastOffset), rendered.lastOffset)
+    ) {
+      kept = rendered.chunks.slice(0, -1);
+      content = markdown.slice(rendered.lastOffset);
+      firstLine = rendered.lastLine;
+      firstOffset = rendered.lastOffset;
+    }
+
+    let cancelled = false;
+    const received: RenderChunk[] = [];
+
+    // Keep the rest of the previous render until its replacement arrives
+    const onChunk = new Channel<RenderChunk>();
+    onChunk.onmessage = (chunk) => {
+      if (cancelled) return;
+      received[chunk.index] = { ...chunk, index: kept.length + chunk.index };
+      setChunks((prev) => [
+        ...kept,
+        ...received,
+        ...prev.slice(kept.length + received.length),
+      ]);
+    };
+
+    invoke<RenderedNote>(TAURI_COMMANDS.RENDER_MARKDOWN_CHUNKED, {
+      content,
+      currentFilePath: currentPath,
+      pixelRatio: window.devicePixelRatio || 1,
+      firstLine,
+      onChunk,
+    })
+      .then((result) => {
+        if (cancelled) return;
+        const chunks = [...kept, ...received.slice(0, result.chunks)];
+        const outline = [
+          ...(rendered?.outline ?? []).filter((heading) => heading.line < firstLine),
+          ...result.outline,
+        ];
+        const lastLine = chunks[chunks.length - 1]?.blocks[0]?.start_line ?? 1;
+        renderedRef.current = {
+          markdown,
+          currentPath,
+          chunks,
+          outline,
+          lastLine,
+          lastOffset: offsetOfLine(markdown, lastLine, firstOffset, firstLine),
+        };
+        setChunks(chunks);
+        onRenderedRef.current?.(outline, markdown);
+      })
       .catch((err) => console.error("Markdown parsing error:", err));
-  }, [markdown]);
 
-  return html;
+    return () => {
+      cancelled = true;
+    };
+  }, [markdown, currentPath, follow]);
+
+  return chunks;
 };
@@ -0,0 +1,46 @@
+import { useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { TAURI_COMMANDS } from "../../../utils/constants";
+import { VaultInfo } from "../../../types";
+
+const SNIPPET_STYLE_ID = "obsidian-snippets-css";
+const USER_STYLE_ID = "user-override-css";
+
+const applySnippets = (css: string) => {
+  if (!css.trim()) {
+    document.getElementById(SNIPPET_STYLE_ID)?.remove();
+    return;
+  }
+
+  let styleEl = document.getElementById(SNIPPET_STYLE_ID) as HTMLStyleElement | null;
+  if (!styleEl) {
+    styleEl = document.createElement("style");
+    styleEl.id = SNIPPET_STYLE_ID;
+    // Vault snippets sit under readtext's own style.css, so it keeps the last word
+    const userStyle = document.getElementById(USER_STYLE_ID);
+    document.head.insertBefore(styleEl, userStyle);
+  }
+  styleEl.textContent = css;
+};
+
+/** Applies the enabled CSS snippets of the Obsidian vault `currentPath` is in. */
+export const useVaultSnippets = (currentPath: string | null) => {
+  useEffect(() => {
+    if (!currentPath) return;
+    let cancelled = false;
+
+    invoke<VaultInfo | null>(TAURI_COMMANDS.GET_VAULT_SETTINGS, { path: currentPath })
+      .then((vault) => {
+        if (cancelled) return;
+        const css = (vault?.snippets ?? [])
+          .map((snippet) => `/* ${snippet.name}.css */\n${snippet.css}`)
+          .join("\n");
+        applySnippets(css);
+      })
+      .catch((err) => console.error("Failed to load vault settings:", err));
+
+    return () => {
+      cancelled = true;
+    };
+  }, [currentPath]);
+};
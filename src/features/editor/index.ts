@@ -3,3 +3,4 @@ export * from "./components/MarkdownRenderer";
 export * from "./hooks/useVim";
 export * from "./hooks/useZoom";
 export * from "./hooks/useAutoSave";
+export * from "./utils/sourceLines";
@@ -1,4 +1,10 @@
+import { HeadingData } from "../../../types";
+
 export interface MarkdownRendererProps {
   content: string;
   currentPath: string | null;
+  /** Whether the note is followed, so appends re-render only its end. */
+  follow?: boolean;
+  /** Called with the note's outline once `content` is fully rendered. */
+  onRendered?: (outline: HeadingData[], content: string) => void;
 }
@@ -33,9 +33,11 @@ export const applyHeadingIds = (container: HTMLElement): void => {
 
 export const highlightCodeBlocks = (container: HTMLElement): void => {
   requestAnimationFrame(() => {
-    const codeBlocks = container.querySelectorAll("pre code");
+    // Blocks of chunks rendered earlier are already highlighted
+    const codeBlocks = container.querySelectorAll<HTMLElement>("pre code:not([data-highlighted])");
     codeBlocks.forEach((block) => {
-      Prism.highlightElement(block as HTMLElement);
+      Prism.highlightElement(block);
+      block.dataset.highlighted = "true";
     });
   });
 };
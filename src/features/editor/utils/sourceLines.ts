@@ -0,0 +1,72 @@
+import { BlockSpan } from "../../../types";
+
+// Rendered blocks carry the markdown line they start on (`data-source-line`),
+// which is how the preview and the editor agree on a position.
+
+interface BlockPosition {
+  line: number;
+  top: number;
+}
+
+/** Source line and offset from the top of `scroller` of every rendered block. */
+const blockPositions = (scroller: HTMLElement): BlockPosition[] => {
+  const scrollerTop = scroller.getBoundingClientRect().top;
+  return Array.from(
+    scroller.querySelectorAll<HTMLElement>("[data-source-line]"),
+  ).map((block) => ({
+    line: Number(block.dataset.sourceLine),
+    top: block.getBoundingClientRect().top - scrollerTop,
+  }));
+};
+
+/**
+ * Source line shown at the top of the preview, interpolated between the
+ * blocks around it so it moves smoothly while scrolling.
+ */
+export const getSourceLineAtTop = (scroller: HTMLElement): number => {
+  let previous: BlockPosition | null = null;
+  for (const block of blockPositions(scroller)) {
+    if (block.top > 0) {
+      if (!previous) return block.line;
+      const fraction = -previous.top / (block.top - previous.top);
+      return previous.line + (block.line - previous.line) * fraction;
+    }
+    previous = block;
+  }
+  return previous?.line ?? 1;
+};
+
+/** Scrolls the preview so source `line` is at the top. */
+export const scrollToSourceLine = (scroller: HTMLElement, line: number): void => {
+  let previous: BlockPosition | null = null;
+  let target: number | null = null;
+  for (const block of blockPositions(scroller)) {
+    if (block.line > line) {
+      target = previous
+        ? previous.top +
+          ((block.top - previous.top) * (line - previous.line)) /
+            (block.line - previous.line)
+        : block.top;
+      break;
+    }
+    previous = block;
+  }
+  target ??= previous?.top ?? 0;
+
+  scroller.scrollTo({ top: scroller.scrollTop + target, behavior: "instant" });
+};
+
+/** Briefly highlights the top-level rendered blocks starting inside `spans`. */
+export const highlightSourceBlocks = (scroller: HTMLElement, spans: BlockSpan[]): void => {
+  scroller
+    .querySelectorAll<HTMLElement>(".render-chunk > [data-source-line]")
+    .forEach((block) => {
+      const line = Number(block.dataset.sourceLine);
+      if (!spans.some((span) => span.start_line <= line && line <= span.end_line)) return;
+
+      block.classList.add("change-highlight");
+      setTimeout(() => {
+        block.classList.remove("change-highlight");
+      }, 2000);
+    });
+};
@@ -1,16 +1,36 @@
 import { useState, useEffect, memo, useCallback, useRef } from "react";
-import { readDir, DirEntry, rename } from "@tauri-apps/plugin-fs";
 import { open } from "@tauri-apps/plugin-dialog";
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { TAURI_COMMANDS } from "../../../utils/constants";
+import { RenamePlan, TreeEntry } from "../../../types";
 import "../styles/explorer.css";
 
 interface FileNode {
   name: string;
   path: string;
   isDirectory: boolean;
+  isAsset: boolean;
+  noteCount: number;
   children?: FileNode[];
   isLoaded?: boolean;
 }
 
+const toFileNode = (entry: TreeEntry): FileNode => ({
+  name: entry.name,
+  path: entry.path,
+  isDirectory: entry.kind === "folder",
+  isAsset: entry.kind === "asset",
+  noteCount: entry.note_count,
+  isLoaded: false,
+});
+
+// Same order as the backend: folders first, then natural name order
+const compareNodes = (a: FileNode, b: FileNode) => {
+  if (a.isDirectory && !b.isDirectory) return -1;
+  if (!a.isDirectory && b.isDirectory) return 1;
+  return a.name.localeCompare(b.name, undefined, { numeric: true, sensitivity: "base" });
+};
+
 interface ExplorerPanelProps {
   currentPath: string | null;
   rootPath?: string | null;
@@ -46,23 +66,26 @@ export const ExplorerPanel = memo(({ currentPath, rootPath, onFileOpen }: Explor
   const loadFolder = useCallback(async (folderPath: string) => {
     setLoading(true);
     try {
-      const entries = await readDir(folderPath);
-      
-      const nodes: FileNode[] = entries
-        .map((entry: DirEntry) => ({
-          name: entry.name || "",
-          path: `${folderPath}/${entry.name}`,
-          isDirectory: entry.isDirectory || false,
-          isLoaded: false,
-        }))
-        .filter((node: FileNode) => node.isDirectory || node.name.endsWith(".md"))
-        .sort((a: FileNode, b: FileNode) => {
-          if (a.isDirectory && !b.isDirectory) return -1;
-          if (!a.isDirectory && b.isDirectory) return 1;
-          return a.name.localeCompare(b.name);
+      // Large trees take a while to count; show entries as they arrive
+      const onBatch = new Channel<TreeEntry[]>();
+      onBatch.onmessage = (batch) => {
+        setLoading(false);
+        setFiles((prev) => {
+          const known = new Set(prev.map((node) => node.path));
+          const added = batch
+            .filter((entry) => entry.parent === folderPath && !known.has(entry.path))
+            .map(toFileNode);
+          return [...prev, ...added].sort(compareNodes);
         });
+      };
 
-      setFiles(nodes);
+      setFiles([]);
+      const entries = await invoke<TreeEntry[]>(TAURI_COMMANDS.LIST_TREE, {
+        path: folderPath,
+        depth: 1,
+        onBatch,
+      });
+      setFiles(entries.map(toFileNode));
     } catch (err) {
       console.error("Failed to load folder:", err);
       setFiles([]);
@@ -74,21 +97,11 @@ export const ExplorerPanel = memo(({ currentPath, rootPath, onFileOpen }: Explor
   // FIX: Recursive folder loading
   const loadSubfolder = useCallback(async (folderPath: string): Promise<FileNode[]> => {
     try {
-      const entries = await readDir(folderPath);
-      
-      return entries
-        .map((entry: DirEntry) => ({
-          name: entry.name || "",
-          path: `${folderPath}/${entry.name}`,
-          isDirectory: entry.isDirectory || false,
-          isLoaded: false,
-        }))
-        .filter((node: FileNode) => node.isDirectory || node.name.endsWith(".md"))
-        .sort((a: FileNode, b: FileNode) => {
-          if (a.isDirectory && !b.isDirectory) return -1;
-          if (!a.isDirectory && b.isDirectory) return 1;
-          return a.name.localeCompare(b.name);
-        });
+      const entries = await invoke<TreeEntry[]>(TAURI_COMMANDS.LIST_TREE, {
+        path: folderPath,
+        depth: 1,
+      });
+      return entries.map(toFileNode);
     } catch (err) {
       console.error("Failed to load subfolder:", err);
       return [];
@@ -127,7 +140,7 @@ export const ExplorerPanel = memo(({ currentPath, rootPath, onFileOpen }: Explor
   const handleFileClick = useCallback((node: FileNode) => {
     if (node.isDirectory) {
       toggleFolder(node.path);
-    } else {
+    } else if (!node.isAsset) {
       onFileOpen(node.path);
     }
   }, [toggleFolder, onFileOpen]);
@@ -162,7 +175,26 @@ export const ExplorerPanel = memo(({ currentPath, rootPath, onFileOpen }: Explor
       const newPath = `${pathParts.join("/")}/${renameValue}`;
 
       if (oldPath !== newPath) {
-        await rename(oldPath, newPath);
+        // Preview which links will be rewritten before touching anything
+        const preview = await invoke<RenamePlan>(TAURI_COMMANDS.RENAME_NOTE, {
+          path: oldPath,
+          newName: renameValue,
+          dryRun: true,
+        });
+        const linkCount = preview.files.reduce((sum, file) => sum + file.edits.length, 0);
+        if (
+          linkCount > 0 &&
+          !window.confirm(
+            `Update ${linkCount} link(s) in ${preview.files.length} file(s) to point to "${renameValue}"?`,
+          )
+        ) {
+          return;
+        }
+
+        await invoke<RenamePlan>(TAURI_COMMANDS.RENAME_NOTE, {
+          path: oldPath,
+          newName: renameValue,
+        });
         
         // Check if we renamed the current folder root
         if (currentFolder && oldPath === currentFolder) {
@@ -213,7 +245,7 @@ export const ExplorerPanel = memo(({ currentPath, rootPath, onFileOpen }: Explor
     return nodes.map((node) => (
       <div key={node.path}>
         <button
-          className={`explorer-item explorer-item-btn ${currentPath === node.path ? "active" : ""}`}
+          className={`explorer-item explorer-item-btn ${currentPath === node.path ? "active" : ""} ${node.isAsset ? "asset" : ""}`}
           style={{ "--depth": depth } as React.CSSProperties}
           onClick={() => handleFileClick(node)}
           onContextMenu={(e) => startRenaming(node, e)}
@@ -244,6 +276,9 @@ export const ExplorerPanel = memo(({ currentPath, rootPath, onFileOpen }: Explor
           ) : (
              <span className="explorer-name">{node.name}</span>
           )}
+          {node.isDirectory && (
+            <span className="explorer-count">{node.noteCount}</span>
+          )}
         </button>
         
         {/* Render children if expanded */}
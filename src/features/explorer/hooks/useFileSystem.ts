@@ -1,29 +1,44 @@
-import { useState, useCallback, useEffect } from "react";
+import { useState, useCallback, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { DEFAULT_CONTENT, TAURI_COMMANDS, EVENTS } from "../../../utils/constants";
+import { FileAppend, FileStamp, FileUpdate, OpenedFile } from "../../../types";
 
 export interface FileSystemHook {
     content: string;
     setContent: (content: string) => void;
+    /** Last change to the open file made outside the app. */
+    lastUpdate: FileUpdate | null;
+    /** Stamp of the content last read from disk, for conflict checks on save. */
+    stamp: FileStamp | null;
     currentPath: string | null;
     setCurrentPath: (path: string | null) => void;
     currentFolder: string | null;
     setCurrentFolder: (folder: string | null) => void;
     readFile: (path: string) => Promise<string>;
     startWatch: (path: string) => Promise<void>;
+    /** Paths followed as they grow, by CLI flag or per tab. */
+    followedPaths: Set<string>;
+    /** Follows `path` or stops; takes effect on its next `startWatch`. */
+    setFollow: (path: string, follow: boolean) => void;
     checkIsDir: (path: string) => Promise<boolean>;
 }
 
 export function useFileSystem(): FileSystemHook {
     const [content, setContent] = useState<string>(DEFAULT_CONTENT);
+    const [lastUpdate, setLastUpdate] = useState<FileUpdate | null>(null);
+    const [stamp, setStamp] = useState<FileStamp | null>(null);
     const [currentPath, setCurrentPath] = useState<string | null>(null);
     const [currentFolder, setCurrentFolder] = useState<string | null>(null);
+    const [followedPaths, setFollowedPaths] = useState<Set<string>>(new Set());
+    // Read by startWatch, which may run before the state above updates
+    const followedRef = useRef<Set<string>>(followedPaths);
 
     const readFile = useCallback(async (path: string): Promise<string> => {
         try {
-            const data = await invoke<string>(TAURI_COMMANDS.READ_FILE, { path });
-            return data;
+            const file = await invoke<OpenedFile>(TAURI_COMMANDS.READ_FILE, { path });
+            setStamp(file.stamp);
+            return file.content;
         } catch (e) {
             console.error("Error reading file:", e);
             throw e;
@@ -32,12 +47,26 @@ export function useFileSystem(): FileSystemHook {
 
     const startWatch = useCallback(async (path: string): Promise<void> => {
         try {
-            await invoke(TAURI_COMMANDS.START_WATCH, { path });
+            await invoke(TAURI_COMMANDS.START_WATCH, {
+                path,
+                follow: followedRef.current.has(path),
+            });
         } catch (e) {
             console.error("Error starting watch:", e);
         }
     }, []);
 
+    const setFollow = useCallback((path: string, follow: boolean) => {
+        const next = new Set(followedRef.current);
+        if (follow) {
+            next.add(path);
+        } else {
+            next.delete(path);
+        }
+        followedRef.current = next;
+        setFollowedPaths(next);
+    }, []);
+
     const checkIsDir = useCallback(async (path: string): Promise<boolean> => {
         try {
             return await invoke<boolean>(TAURI_COMMANDS.IS_DIR, { path });
@@ -50,10 +79,17 @@ export function useFileSystem(): FileSystemHook {
     // Set up file update listener
     useEffect(() => {
         let unlisten: () => void;
+        let unlistenAppend: () => void;
 
         const setupListener = async () => {
-            unlisten = await listen<string>(EVENTS.FILE_UPDATE, (event) => {
-                setContent(event.payload);
+            unlisten = await listen<FileUpdate>(EVENTS.FILE_UPDATE, (event) => {
+                setContent(event.payload.content);
+                setStamp(event.payload.stamp);
+                setLastUpdate(event.payload);
+            });
+            // A followed file sends only what was added to it
+            unlistenAppend = await listen<FileAppend>(EVENTS.FILE_APPEND, (event) => {
+                setContent((prev) => prev + event.payload.text);
             });
         };
 
@@ -61,18 +97,23 @@ export function useFileSystem(): FileSystemHook {
 
         return () => {
             if (unlisten) unlisten();
+            if (unlistenAppend) unlistenAppend();
         };
     }, []);
 
     return {
         content,
         setContent,
+        lastUpdate,
+        stamp,
         currentPath,
         setCurrentPath,
         currentFolder,
         setCurrentFolder,
         readFile,
         startWatch,
+        followedPaths,
+        setFollow,
         checkIsDir,
     };
 }
@@ -11,7 +11,8 @@ import {
   SplitIcon,
   SaveIcon,
   SunIcon,
-  MoonIcon
+  MoonIcon,
+  FollowIcon
 } from "../../../components/Icons";
 
 interface ActivityBarProps {
@@ -25,6 +26,9 @@ interface ActivityBarProps {
   onThemeToggle: () => void;
   isAutoSaving?: boolean;
   lastSaved?: Date | null;
+  /** Whether the open note is followed as it grows. */
+  following?: boolean;
+  onFollowToggle?: () => void;
 }
 
 interface ActivityItem {
@@ -51,6 +55,8 @@ export const ActivityBar = ({
   onThemeToggle,
   isAutoSaving = false,
   lastSaved = null,
+  following = false,
+  onFollowToggle,
 }: ActivityBarProps) => {
   const [showSaved, setShowSaved] = useState(false);
 
@@ -119,6 +125,19 @@ export const ActivityBar = ({
           <SplitIcon />
           {viewMode === "split" && <div className="activity-bar-indicator" />}
         </button>
+
+        {onFollowToggle && (
+          <button
+            className={`activity-bar-item ${following ? "active" : ""}`}
+            onClick={onFollowToggle}
+            title={following ? "Stop following" : "Follow (show appended text and keep the end in view)"}
+            aria-label="Follow"
+            aria-pressed={following}
+          >
+            <FollowIcon />
+            {following && <div className="activity-bar-indicator" />}
+          </button>
+        )}
       </div>
 
       <div className="activity-bar-bottom">
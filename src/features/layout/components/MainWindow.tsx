@@ -1,24 +1,56 @@
 import { Sidebar } from "./Sidebar";
 import { ActivityBar } from "./ActivityBar";
 import "../styles/layout.css";
-import { PanelType, ViewMode } from "../../../types";
-import { MarkdownRenderer, MarkdownEditor, MarkdownEditorHandle, useZoom, useVim, useAutoSave } from "../../editor";
+import {
+  BlockSpan,
+  FileStamp,
+  FileUpdate,
+  HeadingData,
+  PanelType,
+  ReadingAnchor,
+  SaveOutcome,
+  ViewMode,
+} from "../../../types";
+import {
+  MarkdownRenderer,
+  MarkdownEditor,
+  MarkdownEditorHandle,
+  useZoom,
+  useVim,
+  useAutoSave,
+  getSourceLineAtTop,
+  scrollToSourceLine,
+  highlightSourceBlocks,
+} from "../../editor";
 import { useTheme, useConfig } from "../../settings";
 import { useEffect, useRef, useState, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { TAURI_COMMANDS } from "../../../utils/constants";
+import { isRevisionPath } from "../../../utils/revision";
 
 
 export const MainWindow = ({
   content,
+  lastUpdate,
+  stamp = null,
   currentPath,
+  follow = false,
+  onFollowChange,
   onFileOpen,
   onContentChange,
   rootPath,
   defaultActivePanel,
 }: {
   content: string;
+  /** Last change to the open file made outside the app. */
+  lastUpdate?: FileUpdate | null;
+  /** Stamp of `content` as read from disk. */
+  stamp?: FileStamp | null;
   currentPath: string | null;
+  /** Whether the open file is followed as it grows. */
+  follow?: boolean;
+  onFollowChange?: (follow: boolean) => void;
   onFileOpen?: (path: string) => void;
   onContentChange?: (newContent: string) => void;
   rootPath?: string | null;
@@ -29,6 +61,7 @@ export const MainWindow = ({
   const scrollRef = useRef<HTMLDivElement>(null);
   const [activePanel, setActivePanel] = useState<PanelType>(defaultActivePanel || null);
   const [theme, setTheme] = useState<"light" | "dark">("light");
+  const [outline, setOutline] = useState<HeadingData[]>([]);
 
   // Sync theme with config
   useEffect(() => {
@@ -46,13 +79,13 @@ export const MainWindow = ({
 
   // Apply max-width to document
   useEffect(() => {
-    if (config?.max_width) {
+    if (config?.layout?.max_width) {
       document.documentElement.style.setProperty(
         "--user-max-width",
-        config.max_width
+        config.layout.max_width
       );
     }
-  }, [config?.max_width]);
+  }, [config?.layout?.max_width]);
 
   // Listen for live config updates from settings panel
   useEffect(() => {
@@ -65,10 +98,10 @@ export const MainWindow = ({
         // Wait, MainWindow has its own useConfig state.
         // We should update the CSS variable directly here for "immediate" effect
         // or trigger a reload.
-        if (event.payload?.max_width) {
+        if (event.payload?.layout?.max_width) {
           document.documentElement.style.setProperty(
             "--user-max-width",
-            event.payload.max_width
+            event.payload.layout.max_width
           );
         }
       });
@@ -99,7 +132,23 @@ export const MainWindow = ({
       setActivePanel(defaultActivePanel);
     }
   }, [defaultActivePanel]);
-  const [viewMode, setViewMode] = useState<ViewMode>("preview");
+  const [viewMode, setViewModeState] = useState<ViewMode>("preview");
+  // Files opened at a git revision (`HEAD~3:/path/to/doc.md`) are read-only
+  const isReadOnly = isRevisionPath(currentPath);
+  // Source line at the top of the pane being left, restored in the new one
+  const modeSwitchLineRef = useRef<number | null>(null);
+  const setViewMode = useCallback((mode: ViewMode) => {
+    if (isReadOnly && mode !== "preview") return;
+    modeSwitchLineRef.current = scrollRef.current
+      ? getSourceLineAtTop(scrollRef.current)
+      : editorRef.current?.getTopLine() ?? null;
+    setViewModeState(mode);
+  }, [isReadOnly]);
+
+  useEffect(() => {
+    if (isReadOnly) setViewModeState("preview");
+  }, [isReadOnly]);
+
   const [hasUnsavedChanges, setHasUnsavedChanges] = useState(false);
   const [editContent, setEditContent] = useState(content);
   // Track what was last persisted to disk so useAutoSave can detect real changes
@@ -119,6 +168,9 @@ export const MainWindow = ({
   }, [fontSize]);
   useVim(scrollRef);
 
+  // Stamp of the file as last loaded/saved, so saves can detect external edits
+  const fileStampRef = useRef<FileStamp | null>(null);
+
   // Sync editContent with loaded content
   useEffect(() => {
     setEditContent(content);
@@ -127,24 +179,81 @@ export const MainWindow = ({
     setHasUnsavedChanges(false);
   }, [content]);
 
+  // Taken with the content itself, so a write right after loading is not
+  // mistaken for what was loaded
+  useEffect(() => {
+    fileStampRef.current = stamp;
+  }, [stamp]);
+
   const saveTimeoutRef = useRef<number | null>(null);
 
+  // Blocks another program changed in the last update, for "next change"
+  const [changedBlocks, setChangedBlocks] = useState<BlockSpan[]>([]);
+  const [changeIndex, setChangeIndex] = useState(-1);
+
+  useEffect(() => {
+    setChangedBlocks([]);
+  }, [currentPath]);
+
   useEffect(() => {
+    // Our own saves come back as updates too, matching what is being edited
+    if (!lastUpdate || lastUpdate.content === editContent) return;
+
+    setChangedBlocks(lastUpdate.changed_blocks);
+    setChangeIndex(-1);
+    // Give the new version time to render
+    const timer = setTimeout(() => {
+      if (scrollRef.current) {
+        highlightSourceBlocks(scrollRef.current, lastUpdate.changed_blocks);
+      }
+    }, 300);
+    return () => clearTimeout(timer);
+  }, [lastUpdate]);
+
+  const jumpToNextChange = useCallback(() => {
+    if (!scrollRef.current || changedBlocks.length === 0) return;
+    const next = (changeIndex + 1) % changedBlocks.length;
+    setChangeIndex(next);
+    scrollToSourceLine(scrollRef.current, changedBlocks[next].start_line);
+    highlightSourceBlocks(scrollRef.current, [changedBlocks[next]]);
+  }, [changedBlocks, changeIndex]);
+
+  // The saved position of the open note, applied once `content` is rendered
+  const [pendingScroll, setPendingScroll] = useState<{
+    content: string;
+    line: number | null;
+    top: number;
+  } | null>(null);
+  const [renderedContent, setRenderedContent] = useState<string | null>(null);
+
+  const handleRendered = useCallback((outline: HeadingData[], rendered: string) => {
+    setOutline(outline);
+    setRenderedContent(rendered);
+  }, []);
+
+  useEffect(() => {
+    let cancelled = false;
+    setPendingScroll(null);
     const restoreScroll = async () => {
+      // A followed file stays scrolled to its end instead
+      if (follow) return;
       if (currentPath && scrollRef.current) {
         try {
           const key = `scroll-${currentPath}`;
-          const savedPos = await invoke<number | null>("get_cache", { key });
-
-          if (savedPos !== null && savedPos !== undefined) {
-            setTimeout(() => {
-              if (scrollRef.current) {
-                scrollRef.current.scrollTo({
-                  top: Number(savedPos),
-                  behavior: "instant",
+          const savedPos = await invoke<ReadingAnchor | number | null>("get_cache", { key });
+          if (savedPos === null || savedPos === undefined) return;
+
+          // Positions saved as a raw offset by older versions are used as is
+          const line =
+            typeof savedPos === "number"
+              ? null
+              : await invoke<number>(TAURI_COMMANDS.RESOLVE_READING_ANCHOR, {
+                  content,
+                  anchor: savedPos,
                 });
-              }
-            }, 150);
+
+          if (!cancelled) {
+            setPendingScroll({ content, line, top: line === null ? Number(savedPos) : 0 });
           }
         } catch (err) {
           console.error("Failed to restore scroll:", err);
@@ -153,20 +262,59 @@ export const MainWindow = ({
     };
 
     restoreScroll();
+    return () => {
+      cancelled = true;
+    };
   }, [currentPath, content]);
 
+  useEffect(() => {
+    if (!pendingScroll || pendingScroll.content !== renderedContent) return;
+    if (!scrollRef.current) return;
+    if (pendingScroll.line === null) {
+      scrollRef.current.scrollTo({
+        top: pendingScroll.top,
+        behavior: "instant",
+      });
+    } else {
+      scrollToSourceLine(scrollRef.current, pendingScroll.line);
+    }
+    setPendingScroll(null);
+  }, [pendingScroll, renderedContent]);
+
+  // While following, keep the end of the note in view as it grows, unless
+  // the reader scrolled away from it
+  const followBottomRef = useRef(true);
+  useEffect(() => {
+    const scroller = scrollRef.current;
+    const container = scroller?.querySelector("#content");
+    if (!follow || !scroller || !container) return;
+
+    followBottomRef.current = true;
+    const observer = new ResizeObserver(() => {
+      if (followBottomRef.current) {
+        scroller.scrollTo({ top: scroller.scrollHeight, behavior: "instant" });
+      }
+    });
+    observer.observe(container);
+    return () => observer.disconnect();
+  }, [follow, viewMode]);
+
   const handleScroll = (e: React.UIEvent<HTMLElement>) => {
+    if (follow) {
+      const { scrollHeight, scrollTop, clientHeight } = e.currentTarget;
+      followBottomRef.current = scrollHeight - scrollTop - clientHeight < 40;
+    }
+
     // 1. Handle Sync in Split Mode
     if (viewMode === "split" && isScrollingRef.current !== "editor") {
       isScrollingRef.current = "preview";
 
-      const { scrollTop, scrollHeight, clientHeight } = e.currentTarget;
-      const percent = (scrollTop / (scrollHeight - clientHeight)) * 100;
+      const line = getSourceLineAtTop(e.currentTarget);
 
       if (editorRef.current) {
         // Optimize with rAF to prevent jank
         requestAnimationFrame(() => {
-          editorRef.current?.scrollToPercent(percent);
+          editorRef.current?.scrollToLine(line);
         });
       }
 
@@ -177,9 +325,10 @@ export const MainWindow = ({
       }, 100);
     }
 
-    // 2. Handle Scroll Saving (existing logic)
+    // 2. Save the position as an anchor to the content, which the next
+    // version of the file is searched for on reload
     if (!currentPath) return;
-    const scrollTop = e.currentTarget.scrollTop;
+    const scroller = e.currentTarget;
 
     if (saveTimeoutRef.current) {
       window.clearTimeout(saveTimeoutRef.current);
@@ -187,23 +336,26 @@ export const MainWindow = ({
 
     saveTimeoutRef.current = window.setTimeout(() => {
       const key = `scroll-${currentPath}`;
-      invoke("save_cache", { key, value: Math.floor(scrollTop) }).catch(
-        console.error,
-      );
+      invoke<ReadingAnchor | null>(TAURI_COMMANDS.READING_ANCHOR, {
+        content: editContent,
+        line: getSourceLineAtTop(scroller),
+      })
+        .then((anchor) => {
+          if (anchor) return invoke("save_cache", { key, value: anchor });
+        })
+        .catch(console.error);
     }, 300);
   };
 
-  const handleEditorScroll = useCallback((percent: number) => {
+  const handleEditorScroll = useCallback(() => {
     if (viewMode === "split" && isScrollingRef.current !== "preview") {
       isScrollingRef.current = "editor";
 
-      if (scrollRef.current) {
-        const { scrollHeight, clientHeight } = scrollRef.current;
-        const scrollTop = (scrollHeight - clientHeight) * (percent / 100);
-
+      const line = editorRef.current?.getTopLine();
+      if (scrollRef.current && line !== undefined) {
         // Optimize with rAF
         requestAnimationFrame(() => {
-          scrollRef.current?.scrollTo({ top: scrollTop, behavior: "instant" });
+          if (scrollRef.current) scrollToSourceLine(scrollRef.current, line);
         });
       }
 
@@ -226,17 +378,38 @@ export const MainWindow = ({
   }, [content, onContentChange]);
 
   const handleSave = useCallback(async () => {
-    if (!currentPath || !hasUnsavedChanges) return;
+    if (!currentPath || !hasUnsavedChanges || isReadOnly) return;
 
     try {
-      await invoke("save_file", { path: currentPath, content: editContent });
+      let outcome = await invoke<SaveOutcome>(TAURI_COMMANDS.SAVE_FILE, {
+        path: currentPath,
+        content: editContent,
+        expected: fileStampRef.current,
+      });
+
+      if (outcome.status === "conflict") {
+        const overwrite = window.confirm(
+          "This file was changed on disk since it was opened. Overwrite it with your version?",
+        );
+        if (!overwrite) return;
+
+        outcome = await invoke<SaveOutcome>(TAURI_COMMANDS.SAVE_FILE, {
+          path: currentPath,
+          content: editContent,
+          expected: null,
+        });
+      }
+
+      if (outcome.status === "saved") {
+        fileStampRef.current = outcome.stamp;
+      }
       setSavedContent(editContent);
       savedContentRef.current = editContent;
       setHasUnsavedChanges(false);
     } catch (err) {
       console.error("Failed to save file:", err);
     }
-  }, [currentPath, editContent, hasUnsavedChanges]);
+  }, [currentPath, editContent, hasUnsavedChanges, isReadOnly]);
 
   // Auto-save hook
   const autoSaveEnabled = config?.features.auto_save ?? false;
@@ -252,19 +425,18 @@ export const MainWindow = ({
 
 
 
-  // Initial Sync when switching to Split Mode
+  // Keep the same source line at the top when switching modes
   useEffect(() => {
-    if (viewMode === "split") {
-      // Give time for layout to settle
-      setTimeout(() => {
-        const percent = editorRef.current?.getScrollPercent() || 0;
-        if (scrollRef.current) {
-          const { scrollHeight, clientHeight } = scrollRef.current;
-          const scrollTop = (scrollHeight - clientHeight) * (percent / 100);
-          scrollRef.current.scrollTo({ top: scrollTop, behavior: "instant" });
-        }
-      }, 100);
-    }
+    const line = modeSwitchLineRef.current;
+    modeSwitchLineRef.current = null;
+    if (line === null) return;
+
+    // Give time for layout to settle
+    const timer = setTimeout(() => {
+      editorRef.current?.scrollToLine(line);
+      if (scrollRef.current) scrollToSourceLine(scrollRef.current, line);
+    }, 100);
+    return () => clearTimeout(timer);
   }, [viewMode]);
 
   // Keyboard shortcuts for mode switching
@@ -290,7 +462,7 @@ export const MainWindow = ({
 
     window.addEventListener("keydown", handleKeyDown);
     return () => window.removeEventListener("keydown", handleKeyDown);
-  }, []);
+  }, [setViewMode]);
 
   const showEditor = viewMode === "edit" || viewMode === "split";
   const showPreview = viewMode === "preview" || viewMode === "split";
@@ -308,10 +480,17 @@ export const MainWindow = ({
         onThemeToggle={handleThemeToggle}
         isAutoSaving={isAutoSaving}
         lastSaved={lastSaved}
+        following={follow}
+        onFollowToggle={
+          currentPath && !isReadOnly && onFollowChange
+            ? () => onFollowChange(!follow)
+            : undefined
+        }
       />
 
       <Sidebar
         content={showPreview ? editContent : content}
+        outline={outline}
         scrollRef={scrollRef}
         activePanel={activePanel}
         currentPath={currentPath}
@@ -343,10 +522,33 @@ export const MainWindow = ({
             onScroll={handleScroll}
           >
             <div id="content" className="markdown-container">
-              <MarkdownRenderer content={editContent} currentPath={currentPath} />
+              <MarkdownRenderer
+                content={editContent}
+                currentPath={currentPath}
+                follow={follow}
+                onRendered={handleRendered}
+              />
             </div>
           </main>
         )}
+
+        {showPreview && changedBlocks.length > 0 && (
+          <div className="change-navigator">
+            <button
+              onClick={jumpToNextChange}
+              title="Scroll to the next block changed on disk"
+            >
+              Next change ({changeIndex + 1}/{changedBlocks.length})
+            </button>
+            <button
+              className="change-navigator-close"
+              onClick={() => setChangedBlocks([])}
+              title="Dismiss"
+            >
+              ×
+            </button>
+          </div>
+        )}
       </div>
     </div>
   );
@@ -9,6 +9,8 @@ import "../../../styles/panels.css";
 
 interface SidebarProps {
   content: string;
+  /** Outline of the rendered note. */
+  outline: HeadingData[];
   scrollRef: RefObject<HTMLDivElement | null>;
   activePanel: PanelType;
   currentPath: string | null;
@@ -17,12 +19,12 @@ interface SidebarProps {
   theme: "light" | "dark";
 }
 
-const HEADING_SELECTOR = ".prose-wrapper h1, .prose-wrapper h2, .prose-wrapper h3";
-const DEBOUNCE_DELAY_MS = 300;
+const OUTLINE_LEVELS = 3;
 const SCROLL_OFFSET_PX = 40;
 
 export const Sidebar = memo(({ 
   content, 
+  outline,
   scrollRef, 
   activePanel, 
   currentPath,
@@ -35,25 +37,30 @@ export const Sidebar = memo(({
   const [activeHeadingId, setActiveHeadingId] = useState<string>("");
   const [headingElements, setHeadingElements] = useState<Element[]>([]);
   
-  // Parse headings once, store both data and elements
+  // Find the rendered heading of each outline entry by its source line,
+  // once the chunks are in the DOM and have their ids
   useEffect(() => {
-    const parseHeadings = () => {
-      const elements = document.querySelectorAll(HEADING_SELECTOR);
-      const elementsArray = Array.from(elements);
-      
-      setHeadingElements(elementsArray);
-      
-      const extractedHeadings = elementsArray.map((element) => ({
-        level: parseInt(element.tagName.replace("H", ""), 10),
-        text: (element as HTMLElement).innerText,
-        id: element.id,
-      }));
-      setHeadings(extractedHeadings);
-    };
-
-    const debounceTimer = setTimeout(parseHeadings, DEBOUNCE_DELAY_MS);
-    return () => clearTimeout(debounceTimer);
-  }, [content]);
+    const frame = requestAnimationFrame(() => {
+      const found = outline
+        .filter((heading) => heading.level <= OUTLINE_LEVELS)
+        .map((heading) => ({
+          heading,
+          element: document.querySelector(
+            `.prose-wrapper h${heading.level}[data-source-line="${heading.line}"]`
+          ),
+        }))
+        .filter(
+          (entry): entry is { heading: HeadingData; element: Element } =>
+            entry.element !== null
+        );
+
+      setHeadingElements(found.map(({ element }) => element));
+      setHeadings(
+        found.map(({ heading, element }) => ({ ...heading, id: element.id || heading.id }))
+      );
+    });
+    return () => cancelAnimationFrame(frame);
+  }, [outline]);
 
   // Track the topmost visible heading
   useEffect(() => {
@@ -1,5 +1,5 @@
 import { memo } from "react";
-import { useConfig } from "../hooks/useConfig";
+import { useConfig, formatConfigIssue } from "../hooks/useConfig";
 import "../../../styles/settings.css";
 
 interface SettingsPanelProps {
@@ -7,13 +7,13 @@ interface SettingsPanelProps {
 }
 
 export const SettingsPanel = memo(({ theme }: SettingsPanelProps) => {
-  const { config, setConfig, isSaving, error, saveConfig } = useConfig();
+  const { config, setConfig, isSaving, error, issues, saveConfig } = useConfig();
 
   const handleWidthChange = (delta: number) => {
     if (!config) return;
 
     // Parse current width
-    const match = config.max_width.match(/^(\d+)(px|%)?$/);
+    const match = config.layout.max_width.match(/^(\d+)(px|%)?$/);
     if (!match) return;
 
     const value = parseInt(match[1]);
@@ -22,7 +22,7 @@ export const SettingsPanel = memo(({ theme }: SettingsPanelProps) => {
     const newValue = Math.max(200, value + delta);
     const newConfig = {
       ...config,
-      max_width: `${newValue}${unit}`,
+      layout: { ...config.layout, max_width: `${newValue}${unit}` },
     };
 
     setConfig(newConfig);
@@ -42,6 +42,14 @@ export const SettingsPanel = memo(({ theme }: SettingsPanelProps) => {
         <>
           <div className="panel-scroll-area">
             {error && <div className="error-message">{error}</div>}
+            {issues.map((issue, i) => (
+              <div
+                key={i}
+                className={issue.severity === "error" ? "error-message" : "warning-message"}
+              >
+                config.json {formatConfigIssue(issue)}
+              </div>
+            ))}
 
             <section className="settings-group">
               <h4>Instance Mode</h4>
@@ -184,7 +192,7 @@ export const SettingsPanel = memo(({ theme }: SettingsPanelProps) => {
                 <span>Max Width</span>
                 <div className="width-control">
                   <button className="width-btn" onClick={() => handleWidthChange(-50)}>−</button>
-                  <div className="width-value">{config.max_width}</div>
+                  <div className="width-value">{config.layout.max_width}</div>
                   <button className="width-btn" onClick={() => handleWidthChange(50)}>+</button>
                 </div>
               </div>
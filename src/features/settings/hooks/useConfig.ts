@@ -1,18 +1,32 @@
-import { useState, useEffect, useCallback } from "react";
+import { useState, useEffect, useCallback, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { emit } from "@tauri-apps/api/event";
-import { Config } from "../../../types";
+import { emit, listen } from "@tauri-apps/api/event";
+import { Config, ConfigChangedPayload, ConfigIssue, ConfigReport } from "../../../types";
+import { EVENTS, TAURI_COMMANDS } from "../../../utils/constants";
+
+export const formatConfigIssue = (issue: ConfigIssue) => {
+    const location = issue.line !== null ? `line ${issue.line}, column ${issue.column}: ` : "";
+    const field = issue.field ? `${issue.field}: ` : "";
+    return `${location}${field}${issue.message}`;
+};
 
 export function useConfig() {
     const [config, setConfig] = useState<Config | null>(null);
     const [isSaving, setIsSaving] = useState(false);
     const [error, setError] = useState<string | null>(null);
+    const [issues, setIssues] = useState<ConfigIssue[]>([]);
+    // Remember the path used for local overrides so external reloads keep it
+    const lastPathRef = useRef<string | undefined>(undefined);
 
     const loadConfig = useCallback(async (path?: string) => {
+        lastPathRef.current = path;
         try {
             const cfg = await invoke<Config>("get_config", { path });
             setConfig(cfg);
             setError(null);
+
+            const report = await invoke<ConfigReport>(TAURI_COMMANDS.VALIDATE_CONFIG);
+            setIssues(report.issues);
         } catch (e) {
             setError(`Failed to load config: ${e}`);
         }
@@ -43,11 +57,32 @@ export function useConfig() {
         loadConfig();
     }, [loadConfig]);
 
+    // Pick up hand edits to config.json
+    useEffect(() => {
+        let unlisten: (() => void) | undefined;
+
+        listen<ConfigChangedPayload>(EVENTS.CONFIG_CHANGED, (event) => {
+            if (event.payload.error) {
+                setError(`Failed to read config.json: ${event.payload.error}`);
+                return;
+            }
+            loadConfig(lastPathRef.current);
+            setIssues(event.payload.issues);
+        }).then((fn) => {
+            unlisten = fn;
+        });
+
+        return () => {
+            if (unlisten) unlisten();
+        };
+    }, [loadConfig]);
+
     return {
         config,
         setConfig,
         isSaving,
         error,
+        issues,
         loadConfig,
         saveConfig
     };
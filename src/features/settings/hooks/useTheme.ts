@@ -1,29 +1,37 @@
 import { useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { EVENTS } from "../../../utils/constants";
+import { ConfigChangedPayload } from "../../../types";
+
+const USER_STYLE_ID = "user-override-css";
+
+const applyUserCss = (userCss: string) => {
+  if (userCss && userCss.trim().length > 0) {
+    let styleEl = document.getElementById(
+      USER_STYLE_ID,
+    ) as HTMLStyleElement | null;
+
+    if (!styleEl) {
+      styleEl = document.createElement("style");
+      styleEl.id = USER_STYLE_ID;
+      document.head.appendChild(styleEl);
+    }
+
+    styleEl.textContent = userCss;
+  } else {
+    document.getElementById(USER_STYLE_ID)?.remove();
+  }
+};
 
 export const useTheme = () => {
   useEffect(() => {
-    const USER_STYLE_ID = "user-override-css";
+    let unlisten: (() => void) | undefined;
 
     const applyTheme = async () => {
       try {
         const userCss = await invoke<string>("get_user_css");
-
-        if (userCss && userCss.trim().length > 0) {
-          let styleEl = document.getElementById(
-            USER_STYLE_ID,
-          ) as HTMLStyleElement | null;
-
-          if (!styleEl) {
-            styleEl = document.createElement("style");
-            styleEl.id = USER_STYLE_ID;
-            document.head.appendChild(styleEl);
-          }
-
-          styleEl.textContent = userCss;
-        } else {
-          document.getElementById(USER_STYLE_ID)?.remove();
-        }
+        applyUserCss(userCss);
       } catch (err) {
         console.error("Failed to load user CSS from Tauri:", err);
       }
@@ -31,6 +39,15 @@ export const useTheme = () => {
 
     applyTheme();
 
-    return () => {};
+    // Re-apply when style.css is edited on disk
+    listen<ConfigChangedPayload>(EVENTS.CONFIG_CHANGED, (event) => {
+      applyUserCss(event.payload.user_css);
+    }).then((fn) => {
+      unlisten = fn;
+    });
+
+    return () => {
+      if (unlisten) unlisten();
+    };
   }, []);
 };
@@ -9,6 +9,7 @@ import {
   SELECT_FILE_MSG,
   NO_FILES_OPEN,
 } from "../utils/constants";
+import { parseRevisionPath } from "../utils/revision";
 import { Tab, PanelType } from "../types";
 
 interface UseAppInitProps {
@@ -132,13 +133,16 @@ export function useAppInit({ fileSystem, tabs }: UseAppInitProps) {
         }
 
         // It is a file - derive parent folder
-        const parentDir = cliPath.substring(0, cliPath.lastIndexOf("/"));
+        const revision = parseRevisionPath(cliPath);
+        const filePath = revision ? revision.path : cliPath;
+        const parentDir = filePath.substring(0, filePath.lastIndexOf("/"));
         if (parentDir) {
           fileSystem.setCurrentFolder(parentDir);
         }
 
         const tabId = crypto.randomUUID();
-        const fileName = cliPath.split("/").pop() || cliPath;
+        const baseName = filePath.split("/").pop() || filePath;
+        const fileName = revision ? `${baseName} @ ${revision.rev}` : baseName;
 
         if (tabs.instanceModeRef.current) {
           const initialTab = { id: tabId, path: cliPath, fileName };
@@ -146,6 +150,10 @@ export function useAppInit({ fileSystem, tabs }: UseAppInitProps) {
           tabs.setActiveTabId(tabId);
         }
 
+        if (await invoke<boolean>(TAURI_COMMANDS.GET_CLI_FOLLOW)) {
+          fileSystem.setFollow(cliPath, true);
+        }
+
         fileSystem.setCurrentPath(cliPath);
         const data = await fileSystem.readFile(cliPath);
         fileSystem.setContent(data);
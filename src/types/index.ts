@@ -9,6 +9,8 @@ export type PanelType = "explorer" | "outline" | "search" | "settings" | null;
 export type ViewMode = "preview" | "edit" | "split";
 
 export interface Config {
+    $schema?: string;
+    version: number;
     search_paths: string[];
     instance_mode: {
         enabled: boolean;
@@ -22,12 +24,212 @@ export interface Config {
         auto_save: boolean;
         auto_save_delay: number;
     };
+    ignore: string[];
+    attachment_folder?: string | null;
     theme: "light" | "dark";
-    max_width: string;
+    layout: {
+        max_width: string;
+    };
+    remote_images: {
+        enabled: boolean;
+        max_cache_mb: number;
+    };
+}
+
+export interface ConfigIssue {
+    field: string;
+    message: string;
+    severity: "error" | "warning";
+    line: number | null;
+    column: number | null;
+}
+
+export interface ConfigReport {
+    config: Config;
+    issues: ConfigIssue[];
+}
+
+export interface ConfigChangedPayload {
+    config: Config;
+    user_css: string;
+    issues: ConfigIssue[];
+    error: string | null;
+}
+
+export interface FileStamp {
+    modified_ms: number;
+    len: number;
+    hash: string;
+}
+
+export type SaveOutcome =
+    | { status: "saved"; stamp: FileStamp }
+    | { status: "conflict"; current: FileStamp };
+
+export interface VersionInfo {
+    id: string;
+    hash: string;
+    saved_ms: number;
+    size: number;
+}
+
+export interface VersionDiff {
+    version: VersionInfo;
+    unified: string;
+    added: number;
+    removed: number;
+}
+
+export interface BlockSpan {
+    start_line: number;
+    end_line: number;
+}
+
+export interface RenderChunk {
+    index: number;
+    html: string;
+    blocks: BlockSpan[];
+}
+
+/** Returned by `render_markdown_chunked` once every chunk is sent. */
+export interface RenderedNote {
+    chunks: number;
+    outline: HeadingData[];
+}
+
+/** Payload of the `file-update` event. */
+export interface FileUpdate {
+    content: string;
+    /** Blocks of `content` that differ from the previous version on disk. */
+    changed_blocks: BlockSpan[];
+    /** Stamp of `content`; `null` while following the file. */
+    stamp: FileStamp | null;
+}
+
+/** A file as returned by `read_file`. */
+export interface OpenedFile {
+    content: string;
+    /** Stamp of `content` for saving; `null` for read-only revisions. */
+    stamp: FileStamp | null;
+}
+
+/** Payload of the `file-append` event, sent while following a file. */
+export interface FileAppend {
+    text: string;
+}
+
+/** Reading position by content, so it survives edits to the note. */
+export interface ReadingAnchor {
+    heading: string | null;
+    block: number;
+    fingerprint: string;
+    offset: number;
+    line: number;
+}
+
+export interface CommitInfo {
+    id: string;
+    short_id: string;
+    summary: string;
+    author: string;
+    email: string;
+    time: number;
+}
+
+export interface GitRepoInfo {
+    root: string;
+    relative_path: string;
+    branch: string | null;
+    head: CommitInfo | null;
+    tracked: boolean;
+}
+
+export interface DiffHunk {
+    old_start: number;
+    old_lines: number;
+    new_start: number;
+    new_lines: number;
+}
+
+export interface GitDiff {
+    base: string;
+    base_commit: CommitInfo | null;
+    unified: string;
+    hunks: DiffHunk[];
+    changed_blocks: BlockSpan[];
+}
+
+export interface BlockBlame extends BlockSpan {
+    commit: CommitInfo | null;
+    uncommitted: boolean;
+}
+
+export interface TreeEntry {
+    name: string;
+    path: string;
+    parent: string;
+    kind: "folder" | "note" | "asset";
+    note_count: number;
+}
+
+export interface LinkEdit {
+    line: number;
+    before: string;
+    after: string;
+}
+
+export interface RenamePlan {
+    from: string;
+    to: string;
+    files: { path: string; edits: LinkEdit[] }[];
+    applied: boolean;
+}
+
+export type LinkProblemKind = "missing_image" | "dead_wikilink" | "dead_link" | "missing_anchor";
+
+export interface LinkProblem {
+    file: string;
+    line: number;
+    kind: LinkProblemKind;
+    target: string;
+    message: string;
+}
+
+export interface LinkReport {
+    scanned_files: number;
+    problems: LinkProblem[];
+}
+
+export interface VaultInfo {
+    root: string;
+    attachment_folder: string | null;
+    link_format: "shortest" | "relative" | "absolute";
+    use_markdown_links: boolean;
+    css_snippets: string[];
+    snippets: { name: string; css: string }[];
+}
+
+export interface AssetInfo {
+    path: string;
+    size: number;
+}
+
+export interface OrphanReport {
+    orphans: AssetInfo[];
+    total_size: number;
+    /** Quarantined images, by the path they will be restored to */
+    quarantined: AssetInfo[];
+}
+
+export interface AssetMove {
+    from: string;
+    to: string;
 }
 
 export interface HeadingData {
     level: number;
     text: string;
     id: string;
+    /** 1-based source line the heading starts on. */
+    line: number;
 }
@@ -8,17 +8,38 @@ export const TAURI_COMMANDS = {
   START_WATCH: "start_watch",
   GET_INSTANCE_MODE: "get_instance_mode",
   GET_CLI_FILE: "get_cli_file",
+  GET_CLI_FOLLOW: "get_cli_follow",
   REBUILD_INDEX: "rebuild_index",
   CLOSE_APP: "close_app",
   IS_DIR: "is_dir",
+  LIST_TREE: "list_tree",
+  RENAME_NOTE: "rename_note",
+  MOVE_NOTE: "move_note",
+  CHECK_LINKS: "check_links",
+  FIND_ORPHAN_ASSETS: "find_orphan_assets",
+  QUARANTINE_ASSETS: "quarantine_assets",
+  RESTORE_ASSETS: "restore_assets",
+  GET_VAULT_SETTINGS: "get_vault_settings",
   SHOW_WINDOW: "show_window",
   SAVE_FILE: "save_file",
+  LIST_VERSIONS: "list_versions",
+  DIFF_VERSION: "diff_version",
+  RESTORE_VERSION: "restore_version",
+  GIT_REPO_INFO: "git_repo_info",
+  GIT_DIFF: "git_diff",
+  GIT_BLAME_BLOCKS: "git_blame_blocks",
   GET_CACHE: "get_cache",
   SAVE_CACHE: "save_cache",
+  READING_ANCHOR: "reading_anchor",
+  RESOLVE_READING_ANCHOR: "resolve_reading_anchor",
   PARSE_MARKDOWN_TO_HTML: "parse_markdown_to_html",
+  RENDER_MARKDOWN_CHUNKED: "render_markdown_chunked",
+  VALIDATE_CONFIG: "validate_config",
 } as const;
 
 export const EVENTS = {
   FILE_UPDATE: "file-update",
+  FILE_APPEND: "file-append",
   OPEN_FILE: "open-file",
+  CONFIG_CHANGED: "config-changed",
 } as const;
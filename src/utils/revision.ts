@@ -0,0 +1,21 @@
+export interface RevisionPath {
+  rev: string;
+  path: string;
+}
+
+/**
+ * Splits a `<rev>:<absolute path>` spec (as produced by `get_cli_file` for
+ * `readtext HEAD~3:docs/design.md` or `--rev`) into its parts.
+ * Returns null for plain paths, including Windows drive paths like `C:/notes`.
+ */
+export const parseRevisionPath = (spec: string): RevisionPath | null => {
+  if (/^[A-Za-z]:[\\/]/.test(spec)) return null;
+
+  const match = spec.match(/^([^/\\:][^:]*):(\/.*)$/);
+  if (!match) return null;
+
+  return { rev: match[1], path: match[2] };
+};
+
+export const isRevisionPath = (spec: string | null | undefined): boolean =>
+  !!spec && parseRevisionPath(spec) !== null;